@@ -1 +1 @@
-DROP TABLE diet_lists_for_user;
\ No newline at end of file
+DROP TABLE diet_lists;
\ No newline at end of file
@@ -1 +1 @@
-DROP TABLE home_trainings_for_user;
+DROP TABLE trainings;
@@ -1 +1 @@
-DROP TABLE before_sizes;
+DROP TABLE sizes;
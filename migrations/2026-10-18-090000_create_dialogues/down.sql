@@ -0,0 +1 @@
+DROP TABLE dialogues;
\ No newline at end of file
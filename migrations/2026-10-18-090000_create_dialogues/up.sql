@@ -0,0 +1,6 @@
+CREATE TABLE dialogues
+(
+    chat_id    BIGINT PRIMARY KEY,
+    state      jsonb       NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
@@ -0,0 +1,16 @@
+ALTER TABLE sizes
+    ALTER COLUMN user_id DROP NOT NULL,
+    ALTER COLUMN chest DROP NOT NULL,
+    ALTER COLUMN waist DROP NOT NULL,
+    ALTER COLUMN hips DROP NOT NULL,
+    ALTER COLUMN hand_biceps DROP NOT NULL,
+    ALTER COLUMN leg_biceps DROP NOT NULL,
+    ALTER COLUMN calf DROP NOT NULL;
+
+ALTER TABLE trainings
+    ALTER COLUMN created_at TYPE TIMESTAMP,
+    ALTER COLUMN updated_at TYPE TIMESTAMP;
+
+ALTER TABLE diet_lists
+    ALTER COLUMN created_at TYPE TIMESTAMP,
+    ALTER COLUMN updated_at TYPE TIMESTAMP;
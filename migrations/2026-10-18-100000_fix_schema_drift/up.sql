@@ -0,0 +1,25 @@
+ALTER TABLE diet_lists
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ,
+    ALTER COLUMN updated_at TYPE TIMESTAMPTZ;
+
+ALTER TABLE trainings
+    ALTER COLUMN created_at TYPE TIMESTAMPTZ,
+    ALTER COLUMN updated_at TYPE TIMESTAMPTZ;
+
+DELETE FROM sizes
+WHERE user_id IS NULL
+   OR chest IS NULL
+   OR waist IS NULL
+   OR hips IS NULL
+   OR hand_biceps IS NULL
+   OR leg_biceps IS NULL
+   OR calf IS NULL;
+
+ALTER TABLE sizes
+    ALTER COLUMN user_id SET NOT NULL,
+    ALTER COLUMN chest SET NOT NULL,
+    ALTER COLUMN waist SET NOT NULL,
+    ALTER COLUMN hips SET NOT NULL,
+    ALTER COLUMN hand_biceps SET NOT NULL,
+    ALTER COLUMN leg_biceps SET NOT NULL,
+    ALTER COLUMN calf SET NOT NULL;
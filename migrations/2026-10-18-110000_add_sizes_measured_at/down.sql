@@ -0,0 +1,4 @@
+DROP INDEX sizes_user_id_measured_at_idx;
+
+ALTER TABLE sizes
+    DROP COLUMN measured_at;
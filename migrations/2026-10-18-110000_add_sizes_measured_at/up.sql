@@ -0,0 +1,4 @@
+ALTER TABLE sizes
+    ADD COLUMN measured_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX sizes_user_id_measured_at_idx ON sizes (user_id, measured_at);
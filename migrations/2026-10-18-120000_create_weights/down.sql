@@ -0,0 +1,11 @@
+ALTER TABLE users
+    ADD COLUMN weight INT;
+
+UPDATE users
+SET weight = (SELECT w.weight
+              FROM weights w
+              WHERE w.user_id = users.id
+              ORDER BY w.measured_at DESC
+              LIMIT 1);
+
+DROP TABLE weights;
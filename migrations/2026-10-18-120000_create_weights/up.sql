@@ -0,0 +1,18 @@
+CREATE TABLE weights
+(
+    id          UUID PRIMARY KEY,
+    user_id     UUID        NOT NULL,
+    weight      INT         NOT NULL,
+    measured_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX weights_user_id_measured_at_idx ON weights (user_id, measured_at);
+
+INSERT INTO weights (id, user_id, weight, measured_at)
+SELECT gen_random_uuid(), id, weight, now()
+FROM users
+WHERE weight IS NOT NULL;
+
+ALTER TABLE users
+    DROP COLUMN weight;
@@ -0,0 +1,15 @@
+ALTER TABLE diet_lists
+    DROP CONSTRAINT diet_lists_user_id_fkey,
+    ADD CONSTRAINT diet_lists_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id);
+
+ALTER TABLE trainings
+    DROP CONSTRAINT trainings_user_id_fkey,
+    ADD CONSTRAINT trainings_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id);
+
+ALTER TABLE sizes
+    DROP CONSTRAINT sizes_user_id_fkey,
+    ADD CONSTRAINT sizes_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id);
+
+ALTER TABLE weights
+    DROP CONSTRAINT weights_user_id_fkey,
+    ADD CONSTRAINT weights_user_id_fkey FOREIGN KEY (user_id) REFERENCES users (id);
@@ -0,0 +1,11 @@
+CREATE TABLE coach_messages
+(
+    id         UUID PRIMARY KEY,
+    user_id    UUID        NOT NULL,
+    role       VARCHAR     NOT NULL,
+    content    TEXT        NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX coach_messages_user_id_created_at_idx ON coach_messages (user_id, created_at);
@@ -0,0 +1,13 @@
+CREATE TABLE token_usage
+(
+    id                UUID PRIMARY KEY,
+    user_id           UUID        NOT NULL,
+    feature           VARCHAR     NOT NULL,
+    prompt_tokens     INT         NOT NULL,
+    completion_tokens INT         NOT NULL,
+    created_at        TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX token_usage_user_id_created_at_idx ON token_usage (user_id, created_at);
+CREATE INDEX token_usage_created_at_idx ON token_usage (created_at);
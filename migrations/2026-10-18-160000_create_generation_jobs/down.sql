@@ -0,0 +1 @@
+DROP TABLE generation_jobs;
@@ -0,0 +1,17 @@
+CREATE TABLE generation_jobs
+(
+    id              UUID PRIMARY KEY,
+    user_id         UUID        NOT NULL,
+    chat_id         BIGINT      NOT NULL,
+    feature         VARCHAR     NOT NULL,
+    training_status VARCHAR,
+    notes           TEXT,
+    state           VARCHAR     NOT NULL,
+    error           TEXT,
+    created_at      TIMESTAMPTZ NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX generation_jobs_state_idx ON generation_jobs (state);
+CREATE INDEX generation_jobs_user_id_idx ON generation_jobs (user_id);
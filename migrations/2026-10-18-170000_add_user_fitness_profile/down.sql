@@ -0,0 +1,5 @@
+ALTER TABLE users
+    DROP COLUMN goal,
+    DROP COLUMN experience,
+    DROP COLUMN days_per_week,
+    DROP COLUMN session_minutes;
@@ -0,0 +1,5 @@
+ALTER TABLE users
+    ADD COLUMN goal            VARCHAR,
+    ADD COLUMN experience      VARCHAR,
+    ADD COLUMN days_per_week   INT,
+    ADD COLUMN session_minutes INT;
@@ -0,0 +1,9 @@
+CREATE TABLE home_equipment
+(
+    id         UUID PRIMARY KEY,
+    user_id    UUID        NOT NULL,
+    name       VARCHAR     NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE,
+    UNIQUE (user_id, name)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE trainings DROP COLUMN notes;
+ALTER TABLE diet_lists DROP COLUMN notes;
@@ -0,0 +1,2 @@
+ALTER TABLE trainings ADD COLUMN notes TEXT;
+ALTER TABLE diet_lists ADD COLUMN notes TEXT;
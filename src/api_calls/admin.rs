@@ -0,0 +1,122 @@
+use crate::consts::{FEATURE_COACH, FEATURE_DIET, FEATURE_TRAINING};
+use crate::db::repository::Repository;
+use crate::errors::Result;
+use crate::prompts::Prompts;
+use crate::quotas::{start_of_day, start_of_month, Quotas};
+use crate::utils::split_message;
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::Bot;
+use uuid::Uuid;
+
+/// Tokens a user spent this month.
+#[derive(Default)]
+struct Spend {
+    today: i64,
+    month: i64,
+    features: BTreeMap<String, i64>,
+}
+
+/// Sends admins the tokens every user spent today and this month.
+pub async fn usage(
+    bot: Bot,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    quotas: Arc<Quotas>,
+) -> Result<()> {
+    if !is_admin(&bot, &msg, &quotas).await? {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let today = start_of_day(now);
+    let mut spends: HashMap<Uuid, Spend> = HashMap::new();
+    for usage in db.get_token_usage_since(start_of_month(now)).await? {
+        let tokens = i64::from(usage.prompt_tokens) + i64::from(usage.completion_tokens);
+        let spend = spends.entry(usage.user_id).or_default();
+        spend.month += tokens;
+        if usage.created_at >= today {
+            spend.today += tokens;
+        }
+        *spend.features.entry(usage.feature).or_default() += tokens;
+    }
+    let mut spends: Vec<_> = spends.into_iter().collect();
+    spends.sort_by_key(|(_, spend)| std::cmp::Reverse(spend.month));
+
+    let limit = |quota: Option<i64>| {
+        quota.map_or("без ліміту".to_string(), |quota| quota.to_string())
+    };
+    let mut lines = vec![format!(
+        "Витрати токенів за {} (ліміт на день: {}, на місяць: {})",
+        now.format("%m.%Y"),
+        limit(quotas.daily),
+        limit(quotas.monthly)
+    )];
+    if spends.is_empty() {
+        lines.push("Цього місяця запитів до штучного інтелекту не було".to_string());
+    }
+    for (user_id, spend) in spends {
+        let user = match db.get_user_by_id(user_id).await {
+            Ok(user) => format!("{} ({})", user.name, user.phone_number),
+            Err(_) => user_id.to_string(),
+        };
+        let features = spend
+            .features
+            .iter()
+            .map(|(feature, tokens)| format!("{} {}", feature_name(feature), tokens))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(format!(
+            "{}: сьогодні {}, за місяць {} ({})",
+            user, spend.today, spend.month, features
+        ));
+    }
+
+    for chunk in split_message(&lines) {
+        bot.send_message(msg.chat.id, chunk).await?;
+    }
+    Ok(())
+}
+
+fn feature_name(feature: &str) -> &str {
+    match feature {
+        FEATURE_TRAINING => "тренування",
+        FEATURE_DIET => "дієта",
+        FEATURE_COACH => "тренер",
+        feature => feature,
+    }
+}
+
+/// Reads the prompt templates from `PROMPTS_DIR` again.
+pub async fn reload_prompts(
+    bot: Bot,
+    msg: Message,
+    quotas: Arc<Quotas>,
+    prompts: Arc<Prompts>,
+) -> Result<()> {
+    if !is_admin(&bot, &msg, &quotas).await? {
+        return Ok(());
+    }
+
+    let text = match prompts.reload() {
+        Ok(()) => "Шаблони промптів оновлено".to_string(),
+        Err(err) => {
+            log::error!("Cannot reload prompt templates: {}", err);
+            format!("Не вдалося оновити шаблони, залишаю попередні: {}", err)
+        }
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+/// Tells everyone but admins that the command is not for them.
+async fn is_admin(bot: &Bot, msg: &Message, quotas: &Quotas) -> Result<bool> {
+    if msg.from().is_some_and(|user| quotas.is_admin(user.id.0)) {
+        return Ok(true);
+    }
+    bot.send_message(msg.chat.id, "Ця команда доступна тільки адміністраторам")
+        .await?;
+    Ok(false)
+}
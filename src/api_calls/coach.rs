@@ -0,0 +1,264 @@
+use crate::api_calls::menu::main_keyboard;
+use crate::async_openai::metered::MeteredLlm;
+use crate::async_openai::provider::{ChatMessage, ChatRole, LlmProvider, LlmProviders};
+use crate::consts::{COACH_ASSISTANT_ROLE, COACH_USER_ROLE, FEATURE_COACH, GYM_STATE, HOME_STATE};
+use crate::db::models::{CoachMessages, Users};
+use crate::db::repository::Repository;
+use crate::errors::Result;
+use crate::models::{CoachCommands, MenuCommands, MyDialogue, State};
+use crate::plans::diet::diet_text;
+use crate::plans::training::training_text;
+use crate::prompts::{profile_variables, PromptKind, Prompts, Variables};
+use crate::quotas::Quotas;
+use crate::safety::injection::screen;
+use crate::utils::make_keyboard;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::Bot;
+use uuid::Uuid;
+
+/// Roughly 3000 tokens of history, older messages are summarized above it.
+const MAX_HISTORY_CHARS: usize = 12_000;
+
+/// Messages that are never summarized so the latest turns stay word for word.
+const KEEP_RECENT_MESSAGES: usize = 6;
+
+#[allow(clippy::too_many_arguments)]
+pub async fn coach_chat(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    llms: Arc<LlmProviders>,
+    quotas: Arc<Quotas>,
+    prompts: Arc<Prompts>,
+    phone_number: String,
+) -> Result<()> {
+    let Some(text) = msg.text() else {
+        bot.send_message(msg.chat.id, "Напиши своє питання текстом!")
+            .await?;
+        return Ok(());
+    };
+
+    if text == CoachCommands::GoBack.to_string() {
+        let keyboard = main_keyboard();
+        bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        dialogue.update(State::ChangeMenu { phone_number }).await?;
+        return Ok(());
+    }
+
+    let user = db.get_user(&phone_number).await?;
+    let keyboard = make_keyboard(vec![
+        CoachCommands::NewChat.to_string(),
+        CoachCommands::GoBack.to_string(),
+    ]);
+
+    if text == CoachCommands::NewChat.to_string() {
+        log::info!("User {} starts a new coach chat", user.id);
+        db.delete_coach_messages(user.id).await?;
+        bot.send_message(msg.chat.id, "Почнемо спочатку! Що хочеш запитати?")
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        return Ok(());
+    }
+
+    if let Err(err) = screen(text) {
+        bot.send_message(msg.chat.id, err.user_message())
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        return Ok(());
+    }
+
+    if let Err(err) = quotas.check(db.as_ref(), user.id).await {
+        bot.send_message(msg.chat.id, err.user_message())
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        return Ok(());
+    }
+
+    let mut messages = vec![ChatMessage::new(
+        ChatRole::System,
+        coach_context(db.as_ref(), &prompts, &user).await?,
+    )];
+    let history = db.get_coach_messages(user.id).await?;
+    messages.extend(history.iter().map(history_message));
+    messages.push(ChatMessage::new(ChatRole::User, text));
+
+    log::info!(
+        "User {} asks coach with {} messages",
+        user.id,
+        messages.len()
+    );
+    let llm = MeteredLlm::new(llms.get(FEATURE_COACH), db.as_ref(), user.id, FEATURE_COACH);
+    let answer = match llm.chat(&messages).await {
+        Ok(answer) => answer.content,
+        Err(err) => {
+            log::error!("Coach cannot answer user {}: {}", user.id, err);
+            bot.send_message(msg.chat.id, err.user_message())
+                .reply_markup(keyboard.resize_keyboard(true))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    db.add_coach_message(user.id, COACH_USER_ROLE, text).await?;
+    db.add_coach_message(user.id, COACH_ASSISTANT_ROLE, &answer)
+        .await?;
+    bot.send_message(msg.chat.id, answer)
+        .reply_markup(keyboard.resize_keyboard(true))
+        .await?;
+
+    compact_history(db.as_ref(), &llm, &prompts, user.id).await
+}
+
+/// The instructions with everything the bot knows about the user.
+async fn coach_context(db: &dyn Repository, prompts: &Prompts, user: &Users) -> Result<String> {
+    let mut variables = profile_variables(db, user).await?;
+    for (variable, status) in [("gym_training", GYM_STATE), ("home_training", HOME_STATE)] {
+        if let Ok(training) = db.get_training(user.id, status.to_string()).await {
+            variables.insert(variable, training_text(&training.user_trainings)?);
+        }
+    }
+    if let Ok(diet) = db.get_diet_list(user.id).await {
+        variables.insert("diet", diet_text(&diet.diet_list)?);
+    }
+    Ok(prompts.render(PromptKind::Coach, &variables))
+}
+
+fn history_message(message: &CoachMessages) -> ChatMessage {
+    match message.role.as_str() {
+        COACH_USER_ROLE => ChatMessage::new(ChatRole::User, message.content.as_str()),
+        COACH_ASSISTANT_ROLE => ChatMessage::new(ChatRole::Assistant, message.content.as_str()),
+        // The summary retells what the user wrote, so it has no more authority than the user.
+        _ => ChatMessage::new(
+            ChatRole::Assistant,
+            format!("Коротко про нашу попередню розмову: {}", message.content),
+        ),
+    }
+}
+
+/// Replaces the older messages with their summary once the history gets too long.
+/// Without a summary from the model they are dropped, keeping the previous summary.
+/// The transcript goes in its own user message, so nothing the user wrote reaches
+/// the instructions.
+async fn compact_history(
+    db: &dyn Repository,
+    llm: &dyn LlmProvider,
+    prompts: &Prompts,
+    user_id: Uuid,
+) -> Result<()> {
+    let history = db.get_coach_messages(user_id).await?;
+    let length: usize = history
+        .iter()
+        .map(|message| message.content.chars().count())
+        .sum();
+    if length <= MAX_HISTORY_CHARS || history.len() <= KEEP_RECENT_MESSAGES {
+        return Ok(());
+    }
+
+    let old = &history[..history.len() - KEEP_RECENT_MESSAGES];
+    let transcript = old
+        .iter()
+        .map(|message| format!("{}: {}", message.role, message.content))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let messages = [
+        ChatMessage::new(
+            ChatRole::System,
+            prompts.render(PromptKind::CoachSummary, &Variables::new()),
+        ),
+        ChatMessage::new(ChatRole::User, transcript),
+    ];
+    // The old messages stay until a summary replaces them, the next turn tries again.
+    let summary = match llm.chat(&messages).await {
+        Ok(summary) => summary.content,
+        Err(err) => {
+            log::warn!("Cannot summarize coach chat of user {}: {}", user_id, err);
+            return Ok(());
+        }
+    };
+
+    log::info!(
+        "Summarizing {} coach messages of user {}",
+        old.len(),
+        user_id
+    );
+    let until = old[old.len() - 1].created_at;
+    db.summarize_coach_messages(user_id, until, &summary).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_openai::mock::MockProvider;
+    use crate::async_openai::provider::Completion;
+    use crate::db::in_memory::InMemoryDb;
+    use crate::errors::Errors;
+    use async_trait::async_trait;
+    use teloxide::types::UserId;
+
+    /// A provider whose every answer times out.
+    struct FailingProvider;
+
+    #[async_trait]
+    impl LlmProvider for FailingProvider {
+        async fn chat(&self, _messages: &[ChatMessage]) -> Result<Completion> {
+            Err(Errors::LlmTimeout)
+        }
+    }
+
+    /// A user whose chat is long enough to be summarized.
+    async fn user_with_long_chat(db: &InMemoryDb) -> Result<Uuid> {
+        db.insert_user("Test", "+380000000000", &Some(UserId(1)))
+            .await?;
+        let user_id = db.get_user("+380000000000").await?.id;
+        let question = format!("Як тренувати ноги? {}", "Деталі. ".repeat(300));
+        for _ in 0..KEEP_RECENT_MESSAGES {
+            db.add_coach_message(user_id, COACH_USER_ROLE, &question)
+                .await?;
+            db.add_coach_message(user_id, COACH_ASSISTANT_ROLE, "Присідай")
+                .await?;
+        }
+        Ok(user_id)
+    }
+
+    #[tokio::test]
+    async fn summary_keeps_user_text_out_of_instructions() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user_id = user_with_long_chat(&db).await?;
+
+        let llm = MockProvider::scripted(["Клієнт питав про ноги"]);
+        compact_history(&db, &llm, &Prompts::from_env()?, user_id).await?;
+
+        let requests = llm.requests();
+        assert_eq!(requests.len(), 1);
+        let [instructions, transcript] = requests[0].as_slice() else {
+            panic!("expected instructions and transcript");
+        };
+        assert_eq!(instructions.role, ChatRole::System);
+        assert!(!instructions.content.contains("Як тренувати ноги"));
+        assert_eq!(transcript.role, ChatRole::User);
+        assert!(transcript.content.contains("Як тренувати ноги"));
+
+        let history = db.get_coach_messages(user_id).await?;
+        assert_eq!(history.len(), KEEP_RECENT_MESSAGES + 1);
+        let summary = history_message(&history[0]);
+        assert_eq!(summary.role, ChatRole::Assistant);
+        assert!(summary.content.contains("Клієнт питав про ноги"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_summary_keeps_history() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user_id = user_with_long_chat(&db).await?;
+
+        compact_history(&db, &FailingProvider, &Prompts::from_env()?, user_id).await?;
+
+        let history = db.get_coach_messages(user_id).await?;
+        assert_eq!(history.len(), 2 * KEEP_RECENT_MESSAGES);
+        Ok(())
+    }
+}
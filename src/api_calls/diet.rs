@@ -1,111 +1,174 @@
-use crate::async_openai::client::OpenAiClient;
-use crate::consts::{PROMPT_MSG_DIET_WITHOUT_ARGS, PROMPT_MSG_DIET_WITH_ARGS};
-use crate::db::database::Db;
+use crate::api_calls::live::LiveMessage;
+use crate::async_openai::metered::MeteredLlm;
+use crate::async_openai::provider::{LlmProvider, LlmProviders, Progress};
+use crate::consts::FEATURE_DIET;
 use crate::db::models::Users;
+use crate::db::repository::Repository;
 use crate::errors::Result;
+use crate::jobs::{new_job, Jobs};
 use crate::models::{DietCommands, MyDialogue, State};
-use crate::utils::{format_prompt, make_keyboard};
-use std::ops::DerefMut;
+use crate::plans::diet::{diet_text, DietPlan};
+use crate::plans::diff::changes_text;
+use crate::plans::{edit_plan, preview, request_plan};
+use crate::prompts::{edited_notes, profile_variables, user_notes, PromptKind, Prompts};
+use crate::quotas::Quotas;
+use crate::safety::plans::Limits;
+use crate::utils::make_keyboard;
 use std::sync::Arc;
 use teloxide::prelude::*;
 use teloxide::Bot;
-use tokio::sync::Mutex;
+use tokio::sync::watch;
 
 pub async fn add_diet(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
-    open_ai_client: Arc<Mutex<OpenAiClient>>,
+    db: Arc<dyn Repository>,
+    jobs: Arc<Jobs>,
+    quotas: Arc<Quotas>,
     phone_number: String,
 ) -> Result<()> {
     log::info!("User {} is adding diet", phone_number);
-    let mut db = db.lock().await;
     let user = db.get_user(&phone_number).await?;
 
-    let response = process_diet(
-        msg.clone(),
-        open_ai_client,
-        user,
-        PROMPT_MSG_DIET_WITHOUT_ARGS,
-        PROMPT_MSG_DIET_WITH_ARGS,
-        db.deref_mut(),
-    )
-    .await?;
-
-    log::info!("Getting response for user {}", phone_number);
-
-    let keyboard = make_keyboard(vec![
-        DietCommands::AddDiet.to_string(),
-        DietCommands::ShowDiet.to_string(),
-        DietCommands::DeleteDiet.to_string(),
-        DietCommands::GoBack.to_string(),
-    ]);
-
-    bot.send_message(
-        msg.chat.id,
-        format!("Дієта додана! \n\n А ось і воно: \n\n {}", response),
-    )
-    .reply_markup(keyboard.resize_keyboard(true))
-    .await?;
+    let text = if db
+        .count_unfinished_generation_jobs(user.id, FEATURE_DIET)
+        .await?
+        > 0
+    {
+        "Я ще складаю попередню дієту, надішлю її, щойно вона буде готова"
+    } else if let Err(err) = quotas.check(db.as_ref(), user.id).await {
+        err.user_message()
+    } else {
+        let job = new_job(
+            user.id,
+            msg.chat.id,
+            FEATURE_DIET,
+            None,
+            user_notes(msg.text()),
+        );
+        jobs.submit(job).await?;
+        "Складаю дієту, це займе хвилину-дві. Можеш далі користуватися меню, \
+         готову дієту надішлю окремим повідомленням"
+    };
+    bot.send_message(msg.chat.id, text).await?;
 
     dialogue.update(State::DietMenu { phone_number }).await?;
     Ok(())
 }
 
 pub async fn process_diet(
-    msg: Message,
-    open_ai_client: Arc<Mutex<OpenAiClient>>,
+    notes: Option<&str>,
+    llm: &dyn LlmProvider,
+    prompts: &Prompts,
     user: Users,
-    prompt_msg_without_args: &str,
-    prompt_msg_with_args: &str,
-    db: &mut Db,
-) -> Result<String> {
-    let response = if let Some(text) = msg.text() {
-        let prompt = format_prompt(
-            Some(text),
-            prompt_msg_with_args,
-            prompt_msg_without_args,
-            user.clone(),
-        );
-        log::info!("Start sending prompt for diet {}!", prompt);
-        open_ai_client.lock().await.send_message(&prompt).await?
-    } else {
-        let prompt = format_prompt(
-            None,
-            prompt_msg_with_args,
-            prompt_msg_without_args,
-            user.clone(),
-        );
-        log::info!("Start sending prompt for diet {}!", prompt);
-        open_ai_client.lock().await.send_message(&prompt).await?
-    };
+    db: &dyn Repository,
+    progress: &Progress,
+) -> Result<DietPlan> {
+    let variables = profile_variables(db, &user).await?;
+    let limits = Limits::for_user(db, &user, notes).await?;
+    let prompt = prompts.render(PromptKind::Diet, &variables);
+    log::info!("Start sending prompt for diet {}!", prompt);
+    let response = request_plan::<DietPlan>(llm, &prompt, notes, &limits, progress).await?;
 
     let user_id = user.id;
-    db.insert_diet_list(user_id, &response).await?;
+    db.insert_diet_list(
+        user_id,
+        serde_json::to_value(&response)?,
+        notes.map(str::to_string),
+    )
+    .await?;
     Ok(response)
 }
 
+#[allow(clippy::too_many_arguments)]
+pub async fn edit_diet(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    llms: Arc<LlmProviders>,
+    quotas: Arc<Quotas>,
+    prompts: Arc<Prompts>,
+    phone_number: String,
+) -> Result<()> {
+    let Some(request) = msg.text() else {
+        bot.send_message(msg.chat.id, "Напиши текстом, що змінити!")
+            .await?;
+        return Ok(());
+    };
+    log::info!("User {} is editing diet", phone_number);
+    let user = db.get_user(&phone_number).await?;
+
+    match db.get_diet_list(user.id).await {
+        Ok(diet) => {
+            let notes = edited_notes(diet.notes.as_deref(), request);
+            let llm = MeteredLlm::new(llms.get(FEATURE_DIET), db.as_ref(), user.id, FEATURE_DIET);
+            let live = LiveMessage::start(&bot, msg.chat.id, "Змінюю дієту...").await?;
+            let (progress, answer) = watch::channel(String::new());
+            let plan = live
+                .track(answer, preview, async {
+                    quotas.check(db.as_ref(), user.id).await?;
+                    let limits = Limits::for_user(db.as_ref(), &user, Some(&notes)).await?;
+                    edit_plan::<DietPlan>(
+                        &llm,
+                        &prompts,
+                        &diet.diet_list,
+                        request,
+                        &limits,
+                        &progress,
+                    )
+                    .await
+                })
+                .await;
+            let text = match plan {
+                Ok(plan) => {
+                    let before = diet_text(&diet.diet_list)?;
+                    let after = plan.to_string();
+                    db.update_diet_list(user.id, serde_json::to_value(&plan)?, Some(notes))
+                        .await?;
+                    format!(
+                        "Дієта оновлена! {} \n\n А ось і вона: \n\n {}",
+                        changes_text(&before, &after),
+                        after
+                    )
+                }
+                Err(err) => {
+                    log::error!("Cannot edit diet for user {}: {}", phone_number, err);
+                    err.user_message().to_string()
+                }
+            };
+            live.finish(text).await?;
+        }
+        Err(_) => {
+            bot.send_message(msg.chat.id, "Ти ще не додав дієту!")
+                .await?;
+        }
+    }
+    dialogue.update(State::DietMenu { phone_number }).await?;
+    Ok(())
+}
+
 pub async fn show_diet(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     phone_number: String,
 ) -> Result<()> {
     log::info!("User {} is showing diet", phone_number);
-    let mut db = db.lock().await;
     let user = db.get_user(&phone_number).await?;
 
     if let Ok(diet) = db.get_diet_list(user.id).await {
         let keyboard = make_keyboard(vec![
             DietCommands::AddDiet.to_string(),
+            DietCommands::EditDiet.to_string(),
             DietCommands::ShowDiet.to_string(),
             DietCommands::DeleteDiet.to_string(),
             DietCommands::GoBack.to_string(),
         ]);
 
-        let diet: String = serde_json::from_value(diet.diet_list)?;
+        let diet = diet_text(&diet.diet_list)?;
 
         bot.send_message(msg.chat.id, format!("Ось твоя дієта: \n\n {}", diet))
             .reply_markup(keyboard.resize_keyboard(true))
@@ -115,6 +178,7 @@ pub async fn show_diet(
     } else {
         let keyboard = make_keyboard(vec![
             DietCommands::AddDiet.to_string(),
+            DietCommands::EditDiet.to_string(),
             DietCommands::ShowDiet.to_string(),
             DietCommands::DeleteDiet.to_string(),
             DietCommands::GoBack.to_string(),
@@ -133,17 +197,17 @@ pub async fn delete_diet(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     phone_number: String,
 ) -> Result<()> {
     log::info!("User {} is deleting diet", phone_number);
-    let mut db = db.lock().await;
     let user = db.get_user(&phone_number).await?;
 
     db.delete_diet_list(user.id).await?;
 
     let keyboard = make_keyboard(vec![
         DietCommands::AddDiet.to_string(),
+        DietCommands::EditDiet.to_string(),
         DietCommands::ShowDiet.to_string(),
         DietCommands::DeleteDiet.to_string(),
         DietCommands::GoBack.to_string(),
@@ -0,0 +1,162 @@
+use crate::api_calls::trainings::training_keyboard;
+use crate::consts::{COMMON_EQUIPMENT, HOME_STATE};
+use crate::db::repository::Repository;
+use crate::errors::Result;
+use crate::models::{EquipmentCommands, MenuCommands, MyDialogue, State};
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::{KeyboardButton, KeyboardMarkup};
+use teloxide::Bot;
+use uuid::Uuid;
+
+/// Marks the common items the user already has.
+const OWNED: &str = "✅ ";
+
+const MAX_ITEMS: usize = 30;
+const MAX_NAME_CHARS: usize = 40;
+
+/// Names of the equipment the user has at home.
+pub async fn home_equipment(db: &dyn Repository, user_id: Uuid) -> Result<Vec<String>> {
+    Ok(db
+        .get_home_equipment(user_id)
+        .await?
+        .into_iter()
+        .map(|item| item.name)
+        .collect())
+}
+
+pub async fn show_equipment(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    phone_number: String,
+) -> Result<()> {
+    log::info!("User {} is editing home equipment", phone_number);
+    let user = db.get_user(&phone_number).await?;
+    let equipment = home_equipment(db.as_ref(), user.id).await?;
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "{} \n\n Натискай кнопки, щоб додати або прибрати інвентар, чи напиши свій через кому. \
+             Домашні тренування будуть тільки з ним.",
+            equipment_text(&equipment)
+        ),
+    )
+    .reply_markup(equipment_keyboard(&equipment).resize_keyboard(true))
+    .await?;
+    dialogue
+        .update(State::HomeEquipment { phone_number })
+        .await?;
+    Ok(())
+}
+
+pub async fn equipment_menu(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    phone_number: String,
+) -> Result<()> {
+    let Some(text) = msg.text() else {
+        bot.send_message(msg.chat.id, "Напиши назву інвентарю текстом!")
+            .await?;
+        return Ok(());
+    };
+    let user = db.get_user(&phone_number).await?;
+
+    match EquipmentCommands::from(text.to_string()) {
+        EquipmentCommands::Done => {
+            bot.send_message(msg.chat.id, MenuCommands::MyHomeTrainings.to_string())
+                .reply_markup(training_keyboard(HOME_STATE).resize_keyboard(true))
+                .await?;
+            dialogue
+                .update(State::HomeTrainingMenu { phone_number })
+                .await?;
+            return Ok(());
+        }
+        EquipmentCommands::Clear => {
+            log::info!("User {} cleared home equipment", phone_number);
+            db.clear_home_equipment(user.id).await?;
+        }
+        EquipmentCommands::Item(text) => {
+            let mut equipment = home_equipment(db.as_ref(), user.id).await?;
+            for name in text.trim_start_matches(OWNED).split(',') {
+                let name = name.trim();
+                if name.is_empty() {
+                    continue;
+                }
+                if name.chars().count() > MAX_NAME_CHARS {
+                    bot.send_message(msg.chat.id, "Назва інвентарю задовга!")
+                        .await?;
+                    continue;
+                }
+                match equipment.iter().position(|item| same_item(item, name)) {
+                    Some(index) => {
+                        db.delete_home_equipment(user.id, &equipment[index]).await?;
+                        equipment.remove(index);
+                    }
+                    None if equipment.len() >= MAX_ITEMS => {
+                        bot.send_message(msg.chat.id, "У списку вже забагато інвентарю!")
+                            .await?;
+                        break;
+                    }
+                    None => {
+                        db.add_home_equipment(user.id, name).await?;
+                        equipment.push(name.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    let equipment = home_equipment(db.as_ref(), user.id).await?;
+    bot.send_message(msg.chat.id, equipment_text(&equipment))
+        .reply_markup(equipment_keyboard(&equipment).resize_keyboard(true))
+        .await?;
+    Ok(())
+}
+
+fn same_item(first: &str, second: &str) -> bool {
+    first.to_lowercase() == second.to_lowercase()
+}
+
+fn equipment_text(equipment: &[String]) -> String {
+    if equipment.is_empty() {
+        "Інвентарю поки немає, тренування будуть з власною вагою.".to_string()
+    } else {
+        format!("Твій інвентар: {}.", equipment.join(", "))
+    }
+}
+
+/// Common items in two columns, the owned ones marked, then the user's own items
+/// and the commands.
+fn equipment_keyboard(equipment: &[String]) -> KeyboardMarkup {
+    let owned = |name: &str| equipment.iter().any(|item| same_item(item, name));
+    let mut buttons: Vec<String> = COMMON_EQUIPMENT
+        .iter()
+        .map(|name| {
+            if owned(name) {
+                format!("{}{}", OWNED, name)
+            } else {
+                name.to_string()
+            }
+        })
+        .collect();
+    buttons.extend(
+        equipment
+            .iter()
+            .filter(|item| !COMMON_EQUIPMENT.iter().any(|name| same_item(item, name)))
+            .map(|item| format!("{}{}", OWNED, item)),
+    );
+
+    let mut keyboard: Vec<Vec<KeyboardButton>> = buttons
+        .chunks(2)
+        .map(|row| row.iter().cloned().map(KeyboardButton::new).collect())
+        .collect();
+    keyboard.push(vec![
+        KeyboardButton::new(EquipmentCommands::Clear.to_string()),
+        KeyboardButton::new(EquipmentCommands::Done.to_string()),
+    ]);
+    KeyboardMarkup::new(keyboard)
+}
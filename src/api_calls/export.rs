@@ -0,0 +1,109 @@
+use crate::consts::{GYM_STATE, HOME_STATE};
+use crate::db::models::{
+    CoachMessages, DietLists, HomeEquipment, Sizes, Trainings, Users, Weights,
+};
+use crate::db::repository::Repository;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::types::{InputFile, InputMedia, InputMediaDocument};
+use teloxide::Bot;
+
+/// Format of the dates in the CSV files, the same one the size import reads.
+const CSV_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Everything the bot stores about a user.
+#[derive(Serialize)]
+struct Export {
+    exported_at: DateTime<Utc>,
+    user: Users,
+    weights: Vec<Weights>,
+    sizes: Vec<Sizes>,
+    gym_trainings: Vec<Trainings>,
+    home_trainings: Vec<Trainings>,
+    diet_lists: Vec<DietLists>,
+    home_equipment: Vec<HomeEquipment>,
+    coach_messages: Vec<CoachMessages>,
+}
+
+pub async fn export(bot: Bot, msg: Message, db: Arc<dyn Repository>) -> crate::errors::Result<()> {
+    let Some(user) = db
+        .get_user_by_telegram_id(&msg.from().map(|user| user.id))
+        .await?
+    else {
+        bot.send_message(
+            msg.chat.id,
+            "Я не знайшов твоїх даних, спочатку зареєструйся через /start",
+        )
+        .await?;
+        return Ok(());
+    };
+    log::info!("Exporting data of user {}", user.id);
+
+    let export = Export {
+        exported_at: Utc::now(),
+        weights: db.get_weights_by_user(user.id).await?,
+        sizes: db.get_sizes_by_user(user.id).await?.unwrap_or_default(),
+        gym_trainings: db.get_trainings(user.id, GYM_STATE.to_string()).await?,
+        home_trainings: db.get_trainings(user.id, HOME_STATE.to_string()).await?,
+        diet_lists: db.get_diet_lists(user.id).await?,
+        home_equipment: db.get_home_equipment(user.id).await?,
+        coach_messages: db.get_coach_messages(user.id).await?,
+        user,
+    };
+
+    let files = vec![
+        InputMedia::Document(InputMediaDocument::new(
+            InputFile::memory(serde_json::to_vec_pretty(&export)?).file_name("export.json"),
+        )),
+        InputMedia::Document(InputMediaDocument::new(
+            InputFile::memory(sizes_csv(&export.sizes)?).file_name("sizes.csv"),
+        )),
+        InputMedia::Document(InputMediaDocument::new(
+            InputFile::memory(weights_csv(&export.weights)?).file_name("weights.csv"),
+        )),
+    ];
+
+    bot.send_message(
+        msg.chat.id,
+        "Ось усі твої дані: профіль, тренування, дієти, інвентар і розмови з тренером \
+         у export.json, розміри тіла та вага у CSV файлах",
+    )
+    .await?;
+    bot.send_media_group(msg.chat.id, files).await?;
+    Ok(())
+}
+
+fn sizes_csv(sizes: &[Sizes]) -> crate::errors::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(["date", "chest", "waist", "hips", "arm", "thigh", "calf"])?;
+    for size in sizes {
+        writer.write_record([
+            size.measured_at.format(CSV_DATE_FORMAT).to_string(),
+            size.chest.to_string(),
+            size.waist.to_string(),
+            size.hips.to_string(),
+            size.hand_biceps.to_string(),
+            size.leg_biceps.to_string(),
+            size.calf.to_string(),
+        ])?;
+    }
+    Ok(writer
+        .into_inner()
+        .map_err(csv::IntoInnerError::into_error)?)
+}
+
+fn weights_csv(weights: &[Weights]) -> crate::errors::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(["date", "weight"])?;
+    for weight in weights {
+        writer.write_record([
+            weight.measured_at.format(CSV_DATE_FORMAT).to_string(),
+            weight.weight.to_string(),
+        ])?;
+    }
+    Ok(writer
+        .into_inner()
+        .map_err(csv::IntoInnerError::into_error)?)
+}
@@ -0,0 +1,265 @@
+use crate::db::repository::Repository;
+use crate::utils::split_message;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use teloxide::net::Download;
+use teloxide::prelude::*;
+use teloxide::types::Document;
+use teloxide::Bot;
+use uuid::Uuid;
+
+/// Biggest CSV file that is downloaded, a few years of measurements are far below it.
+const MAX_IMPORT_FILE_SIZE: u32 = 512 * 1024;
+
+/// Date formats accepted in the `date` column.
+const DATE_FORMATS: [&str; 3] = ["%Y-%m-%d", "%d.%m.%Y", "%d/%m/%Y"];
+
+/// Columns of the measurements, in the order `Repository::update_size` expects them.
+const SIZE_COLUMNS: [(&str, &str); 6] = [
+    ("chest", "груди"),
+    ("waist", "талія"),
+    ("hips", "бедра"),
+    ("arm", "біцепс руки"),
+    ("thigh", "біцепс ноги"),
+    ("calf", "ікра"),
+];
+
+/// Sizes outside of this range are typos rather than measurements.
+const MIN_SIZE: i32 = 10;
+const MAX_SIZE: i32 = 250;
+
+/// A validated row of the CSV file.
+struct SizeRow {
+    measured_at: DateTime<Utc>,
+    sizes: Vec<String>,
+}
+
+/// The line of a row in the file with the row or the reason it was rejected.
+type ParsedRow = (usize, Result<SizeRow, String>);
+
+/// Imports the sizes from a CSV document and replies with a report of every row.
+pub async fn import_sizes(
+    bot: Bot,
+    msg: Message,
+    document: &Document,
+    user_id: Uuid,
+    db: &dyn Repository,
+) -> crate::errors::Result<()> {
+    if document.file.size > MAX_IMPORT_FILE_SIZE {
+        bot.send_message(msg.chat.id, "Файл завеликий, максимум 512 КБ")
+            .await?;
+        return Ok(());
+    }
+
+    let file = bot.get_file(&document.file.id).await?;
+    let mut content = Vec::new();
+    bot.download_file(&file.path, &mut content).await?;
+    log::info!("Importing sizes for user {}", user_id);
+
+    let rows = match parse_sizes_csv(&content) {
+        Ok(rows) => rows,
+        Err(err) => {
+            bot.send_message(msg.chat.id, format!("Не вдалося прочитати файл: {}", err))
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let total = rows.len();
+    let mut report = Vec::new();
+    let mut accepted = 0;
+    for (line, row) in rows {
+        match row {
+            Ok(row) => {
+                let date = row.measured_at.format("%Y-%m-%d");
+                db.update_size(user_id, row.sizes, row.measured_at).await?;
+                accepted += 1;
+                report.push(format!("Рядок {}: ✅ {}", line, date));
+            }
+            Err(reason) => report.push(format!("Рядок {}: ❌ {}", line, reason)),
+        }
+    }
+    report.push(format!("\nІмпортовано {} з {} рядків", accepted, total));
+
+    for chunk in split_message(&report) {
+        bot.send_message(msg.chat.id, chunk).await?;
+    }
+    Ok(())
+}
+
+/// Reads the header and validates every row. Rows are numbered like in a spreadsheet,
+/// the header being the first one.
+fn parse_sizes_csv(content: &[u8]) -> Result<Vec<ParsedRow>, String> {
+    let content = String::from_utf8_lossy(content);
+    let content = content.trim_start_matches('\u{feff}');
+    let header = content.lines().next().unwrap_or_default();
+    let delimiter = if header.contains(';') && !header.contains(',') {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(content.as_bytes());
+
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|err| err.to_string())?
+        .iter()
+        .map(str::to_lowercase)
+        .collect();
+    let position = |name: &str| headers.iter().position(|header| header == name);
+
+    let date_column = position("date").ok_or("немає колонки date")?;
+    let size_columns = SIZE_COLUMNS
+        .iter()
+        .map(|(name, _)| position(name).ok_or(format!("немає колонки {}", name)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut rows = Vec::new();
+    for (index, record) in reader.records().enumerate() {
+        let line = index + 2;
+        let row = record.map_err(|err| err.to_string()).and_then(|record| {
+            let field = |column: usize| record.get(column).unwrap_or_default();
+            let measured_at = parse_date(field(date_column))?;
+            let sizes = size_columns
+                .iter()
+                .zip(SIZE_COLUMNS)
+                .map(|(&column, (_, label))| parse_size(field(column), label))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(SizeRow { measured_at, sizes })
+        });
+        rows.push((line, row));
+    }
+    Ok(rows)
+}
+
+fn parse_date(value: &str) -> Result<DateTime<Utc>, String> {
+    let date = DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+        .ok_or(format!("дата '{}' не у форматі РРРР-ММ-ДД", value))?;
+    if date > Utc::now().date_naive() {
+        return Err(format!("дата {} ще не настала", value));
+    }
+    let midday = date.and_hms_opt(12, 0, 0).ok_or("неіснуючий час")?;
+    Ok(Utc.from_utc_datetime(&midday))
+}
+
+fn parse_size(value: &str, label: &str) -> Result<String, String> {
+    match value.parse::<i32>() {
+        Ok(size) if (MIN_SIZE..=MAX_SIZE).contains(&size) => Ok(size.to_string()),
+        Ok(size) => Err(format!(
+            "{} {} см поза межами {}-{}",
+            label, size, MIN_SIZE, MAX_SIZE
+        )),
+        Err(_) => Err(format!("{} '{}' не ціле число", label, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "date,chest,waist,hips,arm,thigh,calf";
+
+    type Rows = Vec<(usize, Result<Vec<String>, String>)>;
+
+    /// The line of every row with the sizes it got or the reason it was rejected.
+    fn parse(content: &str) -> Result<Rows, String> {
+        Ok(parse_sizes_csv(content.as_bytes())?
+            .into_iter()
+            .map(|(line, row)| (line, row.map(|row| row.sizes)))
+            .collect())
+    }
+
+    #[test]
+    fn reports_every_row() -> Result<(), String> {
+        let rows = parse(&format!(
+            "{}\n2024-01-15,100,80,95,35,55,38\n15.02.2024,101,79,95,35,55,38\nвчора,1,2,3,4,5,6",
+            HEADER
+        ))?;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].0, 2);
+        assert_eq!(
+            rows[0].1.as_deref(),
+            Ok(["100", "80", "95", "35", "55", "38"]
+                .map(String::from)
+                .as_slice())
+        );
+        assert!(rows[1].1.is_ok());
+        assert_eq!(rows[2].0, 4);
+        assert!(rows[2]
+            .1
+            .as_ref()
+            .is_err_and(|reason| reason.contains("вчора")));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_bad_dates() {
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("31.02.2024").is_err());
+        assert!(parse_date("").is_err());
+        assert!(parse_date("2999-01-01").is_err_and(|reason| reason.contains("ще не настала")));
+        assert!(parse_date("2024-02-29").is_ok());
+        assert!(parse_date("29/02/2024").is_ok());
+    }
+
+    #[test]
+    fn rejects_missing_columns() {
+        assert_eq!(
+            parse("date,chest,waist,hips,arm,thigh\n2024-01-15,1,2,3,4,5").err(),
+            Some("немає колонки calf".to_string())
+        );
+        assert_eq!(
+            parse("chest,waist,hips,arm,thigh,calf").err(),
+            Some("немає колонки date".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_bad_sizes() {
+        assert!(parse_size("abc", "груди").is_err_and(|reason| reason.contains("не ціле число")));
+        assert!(parse_size("95.5", "груди").is_err());
+        assert!(parse_size("-90", "груди").is_err_and(|reason| reason.contains("поза межами")));
+        assert!(parse_size("0", "груди").is_err());
+        assert!(parse_size("251", "груди").is_err());
+        assert_eq!(parse_size("10", "груди"), Ok("10".to_string()));
+        assert_eq!(parse_size("250", "груди"), Ok("250".to_string()));
+    }
+
+    #[test]
+    fn rejects_row_with_missing_value() -> Result<(), String> {
+        let rows = parse(&format!("{}\n2024-01-15,100,80,95,35,55", HEADER))?;
+        assert!(rows[0]
+            .1
+            .as_ref()
+            .is_err_and(|reason| reason.contains("ікра")));
+        Ok(())
+    }
+
+    #[test]
+    fn accepts_any_header_case_and_semicolons() -> Result<(), String> {
+        let rows = parse(
+            "\u{feff}Date; CHEST; Waist; Hips; Arm; Thigh; Calf\n2024-01-15; 100; 80; 95; 35; 55; 38",
+        )?;
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].1.is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn accepts_columns_in_any_order() -> Result<(), String> {
+        let rows = parse("calf,thigh,arm,hips,waist,chest,date\n38,55,35,95,80,100,2024-01-15")?;
+        assert_eq!(
+            rows[0].1.as_deref(),
+            Ok(["100", "80", "95", "35", "55", "38"]
+                .map(String::from)
+                .as_slice())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use crate::errors::Result;
+use std::future::Future;
+use std::time::Duration;
+use teloxide::prelude::*;
+use teloxide::types::{ChatAction, MessageId};
+use teloxide::Bot;
+use tokio::sync::watch;
+
+/// Pause between edits of the message, Telegram limits how often one message can change.
+const EDIT_INTERVAL: Duration = Duration::from_millis(1500);
+
+/// Telegram shows the typing indicator for five seconds after it was sent.
+const TYPING_INTERVAL: Duration = Duration::from_secs(4);
+
+/// A placeholder message that shows an answer while the model writes it.
+pub struct LiveMessage {
+    bot: Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    title: String,
+}
+
+impl LiveMessage {
+    /// Sends the placeholder with `title`, which stays above the preview.
+    pub async fn start(bot: &Bot, chat_id: ChatId, title: &str) -> Result<Self> {
+        let message = bot.send_message(chat_id, title).await?;
+        Ok(Self {
+            bot: bot.clone(),
+            chat_id,
+            message_id: message.id,
+            title: title.to_string(),
+        })
+    }
+
+    /// Runs `generation`, showing `preview` of the answer received so far and the
+    /// typing indicator until it finishes.
+    pub async fn track<T>(
+        &self,
+        answer: watch::Receiver<String>,
+        preview: fn(&str) -> String,
+        generation: impl Future<Output = T>,
+    ) -> T {
+        let mut edits = tokio::time::interval(EDIT_INTERVAL);
+        let mut typing = tokio::time::interval(TYPING_INTERVAL);
+        let mut shown = String::new();
+        tokio::pin!(generation);
+        loop {
+            tokio::select! {
+                result = &mut generation => return result,
+                _ = typing.tick() => {
+                    if let Err(err) = self.bot.send_chat_action(self.chat_id, ChatAction::Typing).await {
+                        log::warn!("Cannot show typing in chat {}: {}", self.chat_id, err);
+                    }
+                }
+                _ = edits.tick() => {
+                    let text = preview(&answer.borrow());
+                    if text.is_empty() || text == shown {
+                        continue;
+                    }
+                    let message = format!("{}\n\n{}", self.title, text);
+                    if let Err(err) = self.bot.edit_message_text(self.chat_id, self.message_id, message).await {
+                        log::warn!("Cannot update message in chat {}: {}", self.chat_id, err);
+                    }
+                    shown = text;
+                }
+            }
+        }
+    }
+
+    /// Replaces the placeholder with the final text, or sends it as a new
+    /// message when the placeholder can't be edited.
+    pub async fn finish(self, text: String) -> Result<()> {
+        if let Err(err) = self
+            .bot
+            .edit_message_text(self.chat_id, self.message_id, text.clone())
+            .await
+        {
+            log::warn!("Cannot finish message in chat {}: {}", self.chat_id, err);
+            self.bot.send_message(self.chat_id, text).await?;
+        }
+        Ok(())
+    }
+
+    /// Sends the final text as a new message, so the user is notified even if the
+    /// placeholder scrolled away, and removes the placeholder.
+    pub async fn deliver(self, text: String) -> Result<()> {
+        self.bot.send_message(self.chat_id, text).await?;
+        if let Err(err) = self.bot.delete_message(self.chat_id, self.message_id).await {
+            log::warn!("Cannot delete message in chat {}: {}", self.chat_id, err);
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,19 @@
 use crate::api_calls::diet::{delete_diet, show_diet};
-use crate::api_calls::trainings::{delete_training, show_trainings};
+use crate::api_calls::equipment::show_equipment;
+use crate::api_calls::import::import_sizes;
+use crate::api_calls::registration::ask_goal;
+use crate::api_calls::trainings::{
+    delete_training, show_training_history, show_trainings, training_keyboard,
+};
 use crate::consts::{GYM_STATE, HOME_STATE};
-use crate::db::database::Db;
+use crate::db::models::Weights;
+use crate::db::repository::Repository;
 use crate::models::{
-    DataCommands, DietCommands, MenuCommands, MyDialogue, State, TrainingsCommands,
+    CoachCommands, DataCommands, DeleteAccountCommands, DietCommands, Experience, Goal,
+    MenuCommands, MyDialogue, State, TrainingsCommands,
 };
 use crate::utils::make_keyboard;
+use chrono::Duration;
 use plotters::backend::BitMapBackend;
 use plotters::chart::ChartBuilder;
 use plotters::element::{Circle, Text};
@@ -13,15 +21,43 @@ use plotters::prelude::{IntoDrawingArea, LogScalable, RED, WHITE};
 use plotters::series::LineSeries;
 use plotters::style::{IntoFont, ShapeStyle, BLACK, BLUE, CYAN, GREEN, MAGENTA, YELLOW};
 use std::collections::HashMap;
-use std::ops::DerefMut;
 use std::path::Path;
 use std::sync::Arc;
 use teloxide::prelude::*;
-use teloxide::types::InputFile;
+use teloxide::types::{InputFile, KeyboardMarkup, KeyboardRemove};
 use teloxide::Bot;
-use tokio::sync::Mutex;
 use uuid::Uuid;
 
+/// Periods for the weight changes shown with the user data.
+const WEEK_DAYS: i64 = 7;
+const MONTH_DAYS: i64 = 30;
+
+/// Shown for the profile answers the user skipped.
+const NOT_SET: &str = "не вказано";
+
+/// Buttons of the main menu.
+pub fn main_keyboard() -> KeyboardMarkup {
+    make_keyboard(vec![
+        MenuCommands::MyGymTrainings.to_string(),
+        MenuCommands::MyHomeTrainings.to_string(),
+        MenuCommands::MyDiet.to_string(),
+        MenuCommands::Data.to_string(),
+        MenuCommands::Coach.to_string(),
+    ])
+}
+
+/// Buttons of the "Мої дані" menu.
+pub fn data_keyboard() -> KeyboardMarkup {
+    make_keyboard(vec![
+        DataCommands::UpdateData.to_string(),
+        DataCommands::UpdateSize.to_string(),
+        DataCommands::ShowData.to_string(),
+        DataCommands::ShowStatistics.to_string(),
+        DataCommands::DeleteAccount.to_string(),
+        DataCommands::GoBack.to_string(),
+    ])
+}
+
 pub async fn change_menu(
     bot: Bot,
     dialogue: MyDialogue,
@@ -30,15 +66,9 @@ pub async fn change_menu(
 ) -> crate::errors::Result<()> {
     if let Some(menu_button) = msg.text() {
         let menu_button = MenuCommands::from(menu_button.to_string());
-        let trainings_buttons = vec![
-            TrainingsCommands::AddTraining.to_string(),
-            TrainingsCommands::DeleteTraining.to_string(),
-            TrainingsCommands::ShowTrainings.to_string(),
-            TrainingsCommands::GoBack.to_string(),
-        ];
-
         let diet_buttons = vec![
             DietCommands::AddDiet.to_string(),
+            DietCommands::EditDiet.to_string(),
             DietCommands::DeleteDiet.to_string(),
             DietCommands::ShowDiet.to_string(),
             DietCommands::GoBack.to_string(),
@@ -47,7 +77,7 @@ pub async fn change_menu(
         match menu_button {
             MenuCommands::MyHomeTrainings => {
                 log::info!("User wants to see home training {}", msg.chat.id);
-                let keyboard = make_keyboard(trainings_buttons);
+                let keyboard = training_keyboard(HOME_STATE);
                 bot.send_message(msg.chat.id, MenuCommands::MyHomeTrainings.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
@@ -57,7 +87,7 @@ pub async fn change_menu(
             }
             MenuCommands::MyGymTrainings => {
                 log::info!("User wants to see gym training {}", msg.chat.id);
-                let keyboard = make_keyboard(trainings_buttons);
+                let keyboard = training_keyboard(GYM_STATE);
                 bot.send_message(msg.chat.id, MenuCommands::MyGymTrainings.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
@@ -75,26 +105,30 @@ pub async fn change_menu(
             }
             MenuCommands::Data => {
                 log::info!("User wants to update data {}", msg.chat.id);
-                let keyboard = make_keyboard(vec![
-                    DataCommands::UpdateData.to_string(),
-                    DataCommands::UpdateSize.to_string(),
-                    DataCommands::ShowData.to_string(),
-                    DataCommands::ShowStatistics.to_string(),
-                    DataCommands::GoBack.to_string(),
-                ]);
+                let keyboard = data_keyboard();
                 bot.send_message(msg.chat.id, MenuCommands::Data.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
                 dialogue.update(State::Data { phone_number }).await?;
             }
-            MenuCommands::GoBack => {
-                log::info!("User wants to go back {}", msg.chat.id);
+            MenuCommands::Coach => {
+                log::info!("User wants to talk to coach {}", msg.chat.id);
                 let keyboard = make_keyboard(vec![
-                    MenuCommands::MyGymTrainings.to_string(),
-                    MenuCommands::MyHomeTrainings.to_string(),
-                    MenuCommands::MyDiet.to_string(),
-                    MenuCommands::Data.to_string(),
+                    CoachCommands::NewChat.to_string(),
+                    CoachCommands::GoBack.to_string(),
                 ]);
+                bot.send_message(
+                    msg.chat.id,
+                    "Я твій тренер! Питай про тренування, харчування чи заміну вправ, \
+     я враховую твої дані та плани і пам'ятаю нашу розмову.",
+                )
+                .reply_markup(keyboard.resize_keyboard(true))
+                .await?;
+                dialogue.update(State::CoachChat { phone_number }).await?;
+            }
+            MenuCommands::GoBack => {
+                log::info!("User wants to go back {}", msg.chat.id);
+                let keyboard = main_keyboard();
                 bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
@@ -107,80 +141,205 @@ pub async fn change_menu(
 
 async fn show_data(
     bot: Bot,
-    db: &mut Db,
+    db: &dyn Repository,
     msg: Message,
     phone_number: String,
 ) -> crate::errors::Result<()> {
     log::info!("User wants to show data {}", msg.chat.id);
     let user = db.get_user(&phone_number).await?;
     let size = db.get_size_by_user(user.id).await?;
-    if let Some(size) = size {
-        bot.send_message(
-            msg.chat.id,
-            format!(
-                "Ваші дані: \n\n\
-                     Вік: {} \n\
-                     Зріст: {} \n\
-                     Вага: {} \n\
-                     Розмір грудей: {} \n\
-                     Розмір талії: {} \n\
-                     Розмір бедер: {} \n\
-                     Розмір біцепсу руки: {} \n\
-                     Розмір біцепсу ноги: {} \n\
-                     Розмір ікри: {}",
-                user.age.unwrap_or_default(),
-                user.height.unwrap_or_default(),
-                user.weight.unwrap_or_default(),
-                size.chest,
-                size.waist,
-                size.hips,
-                size.hand_biceps,
-                size.leg_biceps,
-                size.calf
-            ),
-        )
+    let weights = db.get_weights_by_user(user.id).await?;
+    let weight = weights.last().map(|weight| weight.weight);
+
+    let sizes_text = match size {
+        Some(size) => format!(
+            "Розмір грудей: {} \n\
+                 Розмір талії: {} \n\
+                 Розмір бедер: {} \n\
+                 Розмір біцепсу руки: {} \n\
+                 Розмір біцепсу ноги: {} \n\
+                 Розмір ікри: {} \n\
+                 Дата вимірювання: {}",
+            size.chest,
+            size.waist,
+            size.hips,
+            size.hand_biceps,
+            size.leg_biceps,
+            size.calf,
+            size.measured_at.format("%d.%m.%Y")
+        ),
+        None => "Розміри тіла ще не вводились".to_string(),
+    };
+
+    bot.send_message(
+        msg.chat.id,
+        format!(
+            "Ваші дані: \n\n\
+                 Вік: {} \n\
+                 Зріст: {} \n\
+                 Вага: {} \n\
+                 Зміна ваги за тиждень: {} \n\
+                 Зміна ваги за місяць: {} \n\n\
+                 Мета: {} \n\
+                 Досвід: {} \n\
+                 Днів тренувань на тиждень: {} \n\
+                 Тривалість тренування: {} \n\n\
+                 {}",
+            user.age.unwrap_or_default(),
+            user.height.unwrap_or_default(),
+            weight.unwrap_or_default(),
+            format_weight_delta(weight_delta(&weights, WEEK_DAYS)),
+            format_weight_delta(weight_delta(&weights, MONTH_DAYS)),
+            user.goal
+                .as_deref()
+                .and_then(Goal::from_key)
+                .map_or(NOT_SET.to_string(), |goal| goal.to_string()),
+            user.experience
+                .as_deref()
+                .and_then(Experience::from_key)
+                .map_or(NOT_SET.to_string(), |experience| experience.to_string()),
+            user.days_per_week
+                .map_or(NOT_SET.to_string(), |days| days.to_string()),
+            user.session_minutes
+                .map_or(NOT_SET.to_string(), |minutes| format!("{} хв", minutes)),
+            sizes_text
+        ),
+    )
+    .await?;
+    let keyboard = data_keyboard();
+    bot.send_message(msg.chat.id, MenuCommands::Data.to_string())
+        .reply_markup(keyboard.resize_keyboard(true))
         .await?;
-        let keyboard = make_keyboard(vec![
-            DataCommands::UpdateData.to_string(),
-            DataCommands::UpdateSize.to_string(),
-            DataCommands::ShowData.to_string(),
-            DataCommands::ShowStatistics.to_string(),
-            DataCommands::GoBack.to_string(),
-        ]);
-        bot.send_message(msg.chat.id, MenuCommands::Data.to_string())
-            .reply_markup(keyboard.resize_keyboard(true))
-            .await?;
+    Ok(())
+}
+
+/// Difference between the current weight and the last weigh-in made at least `days` ago.
+fn weight_delta(weights: &[Weights], days: i64) -> Option<i32> {
+    let latest = weights.last()?;
+    let cutoff = chrono::Utc::now() - Duration::days(days);
+    let previous = weights
+        .iter()
+        .rev()
+        .find(|weight| weight.measured_at <= cutoff)?;
+    Some(latest.weight - previous.weight)
+}
+
+fn format_weight_delta(delta: Option<i32>) -> String {
+    match delta {
+        Some(delta) => format!("{:+}", delta),
+        None => "недостатньо даних".to_string(),
+    }
+}
+
+fn draw_weight_chart(path: &str, weights: &[Weights]) -> crate::errors::Result<()> {
+    let first_date = weights[0].measured_at;
+    let last_date = weights[weights.len() - 1].measured_at;
+    let (start_date, end_date) = if first_date == last_date {
+        (
+            first_date - Duration::days(1),
+            last_date + Duration::days(1),
+        )
     } else {
-        bot.send_message(msg.chat.id, "Ви ще не вводили дані!")
-            .await?;
-        let keyboard = make_keyboard(vec![
-            MenuCommands::MyGymTrainings.to_string(),
-            MenuCommands::MyHomeTrainings.to_string(),
-            MenuCommands::MyDiet.to_string(),
-            MenuCommands::Data.to_string(),
-        ]);
-        bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
-            .reply_markup(keyboard.resize_keyboard(true))
-            .await?;
+        (first_date, last_date)
+    };
+    let label_offset = (end_date - start_date) / 100;
+
+    let min_weight = weights
+        .iter()
+        .map(|weight| weight.weight)
+        .min()
+        .unwrap_or(0);
+    let max_weight = weights
+        .iter()
+        .map(|weight| weight.weight)
+        .max()
+        .unwrap_or(0);
+
+    let root = BitMapBackend::new(path, (1280, 720)).into_drawing_area();
+    root.fill(&WHITE)?;
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption("Динаміка ваги", ("sans-serif", 40).into_font())
+        .x_label_area_size(40.0)
+        .y_label_area_size(40.0)
+        .build_cartesian_2d(
+            start_date..end_date + label_offset * 10,
+            (min_weight - 5).as_f64()..(max_weight + 5).as_f64(),
+        )?;
+
+    chart
+        .configure_mesh()
+        .x_label_formatter(&|date| date.format("%d.%m.%Y").to_string())
+        .draw()?;
+
+    chart.draw_series(LineSeries::new(
+        weights
+            .iter()
+            .map(|weight| (weight.measured_at, weight.weight.as_f64())),
+        &BLUE,
+    ))?;
+
+    for weight in weights {
+        let value = weight.weight.as_f64();
+        chart.draw_series(std::iter::once(Circle::new(
+            (weight.measured_at, value),
+            3,
+            ShapeStyle::from(BLUE).filled(),
+        )))?;
+        chart.draw_series(std::iter::once(Text::new(
+            format!("{}", weight.weight),
+            (weight.measured_at + label_offset, value + 0.5),
+            ("sans-serif", 15.0).into_font(),
+        )))?;
     }
+
     Ok(())
 }
 
 async fn show_statistic(
     bot: Bot,
-    db: &mut Db,
+    db: &dyn Repository,
     msg: Message,
     phone_number: String,
 ) -> crate::errors::Result<()> {
     log::info!("User wants to show statistic {}", msg.chat.id);
     let user = db.get_user(&phone_number).await?;
-    let sizes_list = db.get_sizes_by_user(user.id).await?;
+    let sizes_list = db
+        .get_sizes_by_user(user.id)
+        .await?
+        .filter(|sizes_list| !sizes_list.is_empty());
+    let weights = db.get_weights_by_user(user.id).await?;
+
+    let keyboard = data_keyboard();
+
+    if sizes_list.is_none() && weights.is_empty() {
+        bot.send_message(msg.chat.id, "Ви ще не вводили дані!")
+            .await?;
+        bot.send_message(msg.chat.id, MenuCommands::Data.to_string())
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        return Ok(());
+    }
+
+    let path = Path::new("plots");
+    if !path.exists() {
+        std::fs::create_dir(path)?;
+    }
 
     if let Some(sizes_list) = sizes_list {
-        let path = Path::new("plots");
-        if !path.exists() {
-            std::fs::create_dir(path)?;
-        }
+        let first_date = sizes_list[0].measured_at;
+        let last_date = sizes_list[sizes_list.len() - 1].measured_at;
+        // Один замір малюємо посеред дводенного проміжку
+        let (start_date, end_date) = if first_date == last_date {
+            (
+                first_date - Duration::days(1),
+                last_date + Duration::days(1),
+            )
+        } else {
+            (first_date, last_date)
+        };
+        // Відступ для підписів, щоб вони не налазили на крапки
+        let label_offset = (end_date - start_date) / 100;
 
         let path = &format!("plots/stats_plot_{}.png", user.id);
         {
@@ -191,11 +350,15 @@ async fn show_statistic(
                 .caption("Статистика розмірів", ("sans-serif", 40).into_font())
                 .x_label_area_size(40.0)
                 .y_label_area_size(40.0)
-                .build_cartesian_2d(0.0..7.0, 0.0..150.0)?;
+                .build_cartesian_2d(start_date..end_date + label_offset * 10, 0.0..150.0)?;
 
-            chart.configure_mesh().draw()?;
+            chart
+                .configure_mesh()
+                .x_label_formatter(&|date| date.format("%d.%m.%Y").to_string())
+                .draw()?;
 
             for (i, sizes) in sizes_list.iter().enumerate() {
+                let date = sizes.measured_at;
                 for (j, field_value) in [
                     &sizes.chest,
                     &sizes.waist,
@@ -219,7 +382,7 @@ async fn show_statistic(
 
                     let value = field_value.as_f64();
                     chart.draw_series(std::iter::once(Circle::new(
-                        (i as f64, value),
+                        (date, value),
                         3,
                         ShapeStyle::from(color).filled(),
                     )))?;
@@ -227,7 +390,7 @@ async fn show_statistic(
                     // Додавання тексту з числами
                     chart.draw_series(std::iter::once(Text::new(
                         format!("{}", field_value),
-                        ((i as f64) + 0.05, value + 2.0),
+                        (date + label_offset, value + 2.0),
                         ("sans-serif", 15.0).into_font(),
                     )))?;
 
@@ -245,7 +408,7 @@ async fn show_statistic(
                         };
                         let next_value = next_value.as_f64();
                         chart.draw_series(LineSeries::new(
-                            vec![(i as f64, value), ((i + 1) as f64, next_value)],
+                            vec![(date, value), (next_sizes.measured_at, next_value)],
                             &BLACK,
                         ))?;
                     } else {
@@ -261,7 +424,7 @@ async fn show_statistic(
                         };
                         chart.draw_series(std::iter::once(Text::new(
                             explanation,
-                            (i as f64 + 0.18, value + 2.0), // Збільшуємо другий аргумент, щоб текст був ближче до крапки
+                            (date + label_offset * 4, value + 2.0), // Збільшуємо другий аргумент, щоб текст був ближче до крапки
                             ("sans-serif", 15.0).into_font().color(&BLACK),
                         )))?;
                     }
@@ -271,32 +434,22 @@ async fn show_statistic(
 
         let file = InputFile::file(format!("plots/stats_plot_{}.png", user.id));
 
-        let keyboard = make_keyboard(vec![
-            DataCommands::UpdateData.to_string(),
-            DataCommands::UpdateSize.to_string(),
-            DataCommands::ShowData.to_string(),
-            DataCommands::ShowStatistics.to_string(),
-            DataCommands::GoBack.to_string(),
-        ]);
-
         bot.send_photo(msg.chat.id, file)
-            .reply_markup(keyboard.resize_keyboard(true))
+            .reply_markup(keyboard.clone().resize_keyboard(true))
             .await?;
 
         std::fs::remove_file(format!("plots/stats_plot_{}.png", user.id))?;
-    } else {
-        bot.send_message(msg.chat.id, "Ви ще не вводили дані!")
-            .await?;
-        let keyboard = make_keyboard(vec![
-            DataCommands::UpdateData.to_string(),
-            DataCommands::UpdateSize.to_string(),
-            DataCommands::ShowData.to_string(),
-            DataCommands::ShowStatistics.to_string(),
-            DataCommands::GoBack.to_string(),
-        ]);
-        bot.send_message(msg.chat.id, MenuCommands::Data.to_string())
+    }
+
+    if !weights.is_empty() {
+        let path = format!("plots/weight_plot_{}.png", user.id);
+        draw_weight_chart(&path, &weights)?;
+
+        bot.send_photo(msg.chat.id, InputFile::file(&path))
             .reply_markup(keyboard.resize_keyboard(true))
             .await?;
+
+        std::fs::remove_file(&path)?;
     }
     Ok(())
 }
@@ -305,7 +458,7 @@ pub async fn home_training_menu(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     phone_number: String,
 ) -> crate::errors::Result<()> {
     log::info!("User in home training menu {}", msg.chat.id);
@@ -327,6 +480,21 @@ pub async fn home_training_menu(
                     })
                     .await?;
             }
+            TrainingsCommands::EditTraining => {
+                log::info!("User wants to edit training {}", msg.chat.id);
+                bot.send_message(
+                    msg.chat.id,
+                    "Напиши, що змінити в тренуванні, наприклад: без бігу по середах. \n\n\
+     Решта тренування залишиться як є.",
+                )
+                .await?;
+                dialogue
+                    .update(State::EditTraining {
+                        phone_number,
+                        training_state: HOME_STATE.to_string(),
+                    })
+                    .await?;
+            }
             TrainingsCommands::DeleteTraining => {
                 log::info!("User wants to delete training {}", msg.chat.id);
                 bot.send_message(msg.chat.id, "Видалити тренування").await?;
@@ -351,14 +519,24 @@ pub async fn home_training_menu(
                 )
                 .await?
             }
+            TrainingsCommands::History => {
+                log::info!("User wants to see training history {}", msg.chat.id);
+                show_training_history(
+                    bot.clone(),
+                    dialogue.clone(),
+                    msg.clone(),
+                    db,
+                    (phone_number.clone(), HOME_STATE.to_string()),
+                )
+                .await?
+            }
+            TrainingsCommands::Equipment => {
+                log::info!("User wants to edit home equipment {}", msg.chat.id);
+                show_equipment(bot, dialogue, msg, db, phone_number).await?
+            }
             TrainingsCommands::GoBack => {
                 log::info!("User wants to go back {}", msg.chat.id);
-                let keyboard = make_keyboard(vec![
-                    MenuCommands::MyGymTrainings.to_string(),
-                    MenuCommands::MyHomeTrainings.to_string(),
-                    MenuCommands::MyDiet.to_string(),
-                    MenuCommands::Data.to_string(),
-                ]);
+                let keyboard = main_keyboard();
                 bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
@@ -373,7 +551,7 @@ pub async fn gym_training_menu(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     phone_number: String,
 ) -> crate::errors::Result<()> {
     if let Some(training_button) = msg.text() {
@@ -391,6 +569,21 @@ pub async fn gym_training_menu(
                     })
                     .await?;
             }
+            TrainingsCommands::EditTraining => {
+                log::info!("User wants to edit training {}", msg.chat.id);
+                bot.send_message(
+                    msg.chat.id,
+                    "Напиши, що змінити в тренуванні, наприклад: без бігу по середах. \n\n\
+     Решта тренування залишиться як є.",
+                )
+                .await?;
+                dialogue
+                    .update(State::EditTraining {
+                        phone_number,
+                        training_state: GYM_STATE.to_string(),
+                    })
+                    .await?;
+            }
             TrainingsCommands::DeleteTraining => {
                 log::info!("User wants to delete training {}", msg.chat.id);
                 bot.send_message(msg.chat.id, "Видалити тренування").await?;
@@ -415,14 +608,27 @@ pub async fn gym_training_menu(
                 )
                 .await?;
             }
+            TrainingsCommands::History => {
+                log::info!("User wants to see training history {}", msg.chat.id);
+                show_training_history(
+                    bot.clone(),
+                    dialogue.clone(),
+                    msg.clone(),
+                    db,
+                    (phone_number.clone(), GYM_STATE.to_string()),
+                )
+                .await?;
+            }
+            TrainingsCommands::Equipment => {
+                bot.send_message(
+                    msg.chat.id,
+                    "Інвентар потрібен тільки для домашніх тренувань!",
+                )
+                .await?;
+            }
             TrainingsCommands::GoBack => {
                 log::info!("User wants to go back {}", msg.chat.id);
-                let keyboard = make_keyboard(vec![
-                    MenuCommands::MyGymTrainings.to_string(),
-                    MenuCommands::MyHomeTrainings.to_string(),
-                    MenuCommands::MyDiet.to_string(),
-                    MenuCommands::Data.to_string(),
-                ]);
+                let keyboard = main_keyboard();
                 bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
@@ -438,7 +644,7 @@ pub async fn diet_menu(
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
     if let Some(training_button) = msg.text() {
         let training_button = DietCommands::from(training_button.to_string());
@@ -450,6 +656,16 @@ pub async fn diet_menu(
                 Також, потрібно буде трохи зачекати, генерую для тебе дієту)").await?;
                 dialogue.update(State::AddDiet { phone_number }).await?;
             }
+            DietCommands::EditDiet => {
+                log::info!("User wants to edit diet {}", msg.chat.id);
+                bot.send_message(
+                    msg.chat.id,
+                    "Напиши, що змінити в дієті, наприклад: заміни рибу. \n\n\
+     Решта дієти залишиться як є.",
+                )
+                .await?;
+                dialogue.update(State::EditDiet { phone_number }).await?;
+            }
             DietCommands::DeleteDiet => {
                 log::info!("User wants to delete training {}", msg.chat.id);
                 bot.send_message(msg.chat.id, "Видалити дієту").await?;
@@ -476,12 +692,7 @@ pub async fn diet_menu(
             }
             DietCommands::GoBack => {
                 log::info!("User wants to go back {}", msg.chat.id);
-                let keyboard = make_keyboard(vec![
-                    MenuCommands::MyGymTrainings.to_string(),
-                    MenuCommands::MyHomeTrainings.to_string(),
-                    MenuCommands::MyDiet.to_string(),
-                    MenuCommands::Data.to_string(),
-                ]);
+                let keyboard = main_keyboard();
                 bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
                     .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
@@ -497,9 +708,8 @@ pub async fn update_data(
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
-    let mut db = db.lock().await;
     match msg.text() {
         Some(data) => {
             let menu = DataCommands::from(data.to_string());
@@ -511,7 +721,8 @@ pub async fn update_data(
                     bot.send_message(
                         msg.chat.id,
                         "Хочете оновити дані? \n\n\
-     Добре, тільки скидуйте у такому вигляді: вік: 21, зріст: 185, вага: 112",
+     Добре, тільки скидуйте у такому вигляді: вік: 21, зріст: 185, вага: 112 \n\n\
+     Потім я запитаю про мету, досвід і скільки часу ти можеш тренуватися",
                     )
                     .await?;
                     dialogue.update(State::UpdateData { phone_number }).await?;
@@ -522,36 +733,39 @@ pub async fn update_data(
                     bot.send_message(
                         msg.chat.id,
                         "Хочете оновити розмір м'язів? \n\n\
-     Добре, тільки скидуйте у такому вигляді: 108 - груди, 105 - талія, 123 - бедра, 39 - біцепс руки, 72 - біцепс ноги, 45 - ікра",
+     Добре, тільки скидуйте у такому вигляді: 108 - груди, 105 - талія, 123 - бедра, 39 - біцепс руки, 72 - біцепс ноги, 45 - ікра \n\n\
+     Або надішліть CSV файл з колонками date, chest, waist, hips, arm, thigh, calf, щоб імпортувати всю історію",
                     )
                         .await?;
                     dialogue.update(State::UpdateSize { phone_number }).await?;
                 }
                 DataCommands::ShowData => {
-                    show_data(
-                        bot.clone(),
-                        db.deref_mut(),
-                        msg.clone(),
-                        phone_number.clone(),
-                    )
-                    .await?;
+                    show_data(bot.clone(), db.as_ref(), msg.clone(), phone_number.clone()).await?;
                 }
                 DataCommands::ShowStatistics => {
-                    show_statistic(
-                        bot.clone(),
-                        db.deref_mut(),
-                        msg.clone(),
-                        phone_number.clone(),
+                    show_statistic(bot.clone(), db.as_ref(), msg.clone(), phone_number.clone())
+                        .await?;
+                }
+                DataCommands::DeleteAccount => {
+                    log::info!("User wants to delete account {}", msg.chat.id);
+                    let keyboard = make_keyboard(vec![
+                        DeleteAccountCommands::Confirm.to_string(),
+                        DeleteAccountCommands::Cancel.to_string(),
+                    ]);
+                    bot.send_message(
+                        msg.chat.id,
+                        "Ти точно хочеш видалити акаунт? \n\n\
+     Буде видалено твій профіль, номер телефону, пошту, тренування, дієти, вагу та розміри тіла. \
+     Відновити їх буде неможливо!",
                     )
+                    .reply_markup(keyboard.resize_keyboard(true))
                     .await?;
+                    dialogue
+                        .update(State::DeleteAccount { phone_number })
+                        .await?;
                 }
                 DataCommands::GoBack => {
-                    let keyboard = make_keyboard(vec![
-                        MenuCommands::MyGymTrainings.to_string(),
-                        MenuCommands::MyHomeTrainings.to_string(),
-                        MenuCommands::MyDiet.to_string(),
-                        MenuCommands::Data.to_string(),
-                    ]);
+                    let keyboard = main_keyboard();
                     bot.send_message(msg.chat.id, MenuCommands::GoBack.to_string())
                         .reply_markup(keyboard.resize_keyboard(true))
                         .await?;
@@ -574,46 +788,48 @@ pub async fn update_data_data(
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
-    let mut db = db.lock().await;
     match msg.text() {
         Some(data) => {
             let user = db.get_user(&phone_number).await?;
             let data = parse_string(data);
-            update_age(
+            // Both ask again on invalid input, the goal questions wait for valid data.
+            let age_updated = update_age(
                 data.clone(),
                 bot.clone(),
                 msg.clone(),
                 phone_number.clone(),
-                db.deref_mut(),
+                db.as_ref(),
                 dialogue.clone(),
                 user.id,
             )
             .await?;
+            if !age_updated {
+                return Ok(());
+            }
 
-            update_height(
+            let height_updated = update_height(
                 data.clone(),
                 bot.clone(),
                 msg.clone(),
                 phone_number.clone(),
-                db.deref_mut(),
+                db.as_ref(),
                 dialogue.clone(),
                 user.id,
             )
             .await?;
+            if !height_updated {
+                return Ok(());
+            }
 
-            let keyboard = make_keyboard(vec![
-                DataCommands::UpdateData.to_string(),
-                DataCommands::UpdateSize.to_string(),
-                DataCommands::ShowData.to_string(),
-                DataCommands::ShowStatistics.to_string(),
-                DataCommands::GoBack.to_string(),
-            ]);
-            bot.send_message(msg.chat.id, "Дані оновлено!")
-                .reply_markup(keyboard.resize_keyboard(true))
+            ask_goal(&bot, msg.chat.id).await?;
+            dialogue
+                .update(State::GetGoal {
+                    phone_number,
+                    registration: false,
+                })
                 .await?;
-            dialogue.update(State::Data { phone_number }).await?;
         }
         None => {
             bot.send_message(
@@ -633,9 +849,20 @@ pub async fn update_size(
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
-    let mut db = db.lock().await;
+    if let Some(document) = msg.document() {
+        let user = db.get_user(&phone_number).await?;
+        import_sizes(bot.clone(), msg.clone(), document, user.id, db.as_ref()).await?;
+
+        let keyboard = data_keyboard();
+        bot.send_message(msg.chat.id, "Імпорт завершено!")
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        dialogue.update(State::Data { phone_number }).await?;
+        return Ok(());
+    }
+
     match msg.text() {
         Some(data) => {
             let user = db.get_user(&phone_number).await?;
@@ -647,15 +874,9 @@ pub async fn update_size(
                 phone_number.clone(),
             )
             .await?;
-            db.update_size(user.id, data).await?;
-
-            let keyboard = make_keyboard(vec![
-                DataCommands::UpdateData.to_string(),
-                DataCommands::UpdateSize.to_string(),
-                DataCommands::ShowData.to_string(),
-                DataCommands::ShowStatistics.to_string(),
-                DataCommands::GoBack.to_string(),
-            ]);
+            db.update_size(user.id, data, chrono::Utc::now()).await?;
+
+            let keyboard = data_keyboard();
             bot.send_message(msg.chat.id, "Розмір м'язів оновлено!")
                 .reply_markup(keyboard.resize_keyboard(true))
                 .await?;
@@ -674,17 +895,18 @@ pub async fn update_size(
     Ok(())
 }
 
+/// Saves the age, `false` when it is missing or not a number and the user is asked again.
 async fn update_age(
     data: HashMap<String, String>,
     bot: Bot,
     msg: Message,
     phone_number: String,
-    db: &mut Db,
+    db: &dyn Repository,
     dialogue: MyDialogue,
     id: Uuid,
-) -> crate::errors::Result<()> {
-    let age = match data.get("вік") {
-        Some(age) => age.parse::<i32>()?,
+) -> crate::errors::Result<bool> {
+    let age = match data.get("вік").and_then(|age| age.parse::<i32>().ok()) {
+        Some(age) => age,
         None => {
             bot.send_message(msg.chat.id, "Вік не валідний!").await?;
             dialogue
@@ -692,26 +914,29 @@ async fn update_age(
                     phone_number: phone_number.clone(),
                 })
                 .await?;
-            return Ok(());
+            return Ok(false);
         }
     };
 
     db.update_age(id, age).await?;
 
-    Ok(())
+    Ok(true)
 }
 
+/// Saves the height and weight, `false` when either is missing or not a number
+/// and the user is asked again.
 async fn update_height(
     data: HashMap<String, String>,
     bot: Bot,
     msg: Message,
     phone_number: String,
-    db: &mut Db,
+    db: &dyn Repository,
     dialogue: MyDialogue,
     id: Uuid,
-) -> crate::errors::Result<()> {
-    let (height, weight) = match (data.get("зріст"), data.get("вага")) {
-        (Some(height), Some(weight)) => (height.parse::<i32>()?, weight.parse::<i32>()?),
+) -> crate::errors::Result<bool> {
+    let parse = |key: &str| data.get(key).and_then(|value| value.parse::<i32>().ok());
+    let (height, weight) = match (parse("зріст"), parse("вага")) {
+        (Some(height), Some(weight)) => (height, weight),
         _ => {
             bot.send_message(msg.chat.id, "Висота та вага не валідні!")
                 .await?;
@@ -720,13 +945,13 @@ async fn update_height(
                     phone_number: phone_number.clone(),
                 })
                 .await?;
-            return Ok(());
+            return Ok(false);
         }
     };
 
     db.update_height_and_weight(id, height, weight).await?;
 
-    Ok(())
+    Ok(true)
 }
 
 fn parse_string(data: &str) -> HashMap<String, String> {
@@ -854,3 +1079,41 @@ async fn parse_string_size(
 
     Ok(result_vec)
 }
+
+pub async fn delete_account(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    phone_number: String,
+    db: Arc<dyn Repository>,
+) -> crate::errors::Result<()> {
+    match msg.text() {
+        Some(data) => match DeleteAccountCommands::from(data.to_string()) {
+            DeleteAccountCommands::Confirm => {
+                log::info!("Deleting account {}", msg.chat.id);
+                db.delete_user(&phone_number).await?;
+                dialogue.exit().await?;
+                bot.send_message(
+                    msg.chat.id,
+                    "Акаунт і всі твої дані видалено. \n\n\
+     Якщо захочеш повернутися, натисни /start",
+                )
+                .reply_markup(KeyboardRemove::new())
+                .await?;
+            }
+            DeleteAccountCommands::Cancel => {
+                let keyboard = data_keyboard();
+                bot.send_message(msg.chat.id, "Добре, акаунт залишається!")
+                    .reply_markup(keyboard.resize_keyboard(true))
+                    .await?;
+                dialogue.update(State::Data { phone_number }).await?;
+            }
+        },
+        None => {
+            bot.send_message(msg.chat.id, "На жаль, я не розумію тебе!")
+                .await?;
+        }
+    }
+
+    Ok(())
+}
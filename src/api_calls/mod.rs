@@ -1,5 +1,11 @@
+pub mod admin;
 pub mod basic_methods;
+pub mod coach;
 pub mod diet;
+pub mod equipment;
+pub mod export;
+pub mod import;
+pub mod live;
 pub mod menu;
 pub mod registration;
 pub mod trainings;
@@ -1,24 +1,28 @@
-use crate::db::database::Db;
-use crate::models::{MenuCommands, MyDialogue, State};
-use crate::utils::{is_valid_email, make_keyboard};
+use crate::api_calls::menu::{data_keyboard, main_keyboard};
+use crate::consts::SKIP_BUTTON;
+use crate::db::repository::Repository;
+use crate::models::{Experience, Goal, MyDialogue, State};
+use crate::utils::{is_valid_email, make_column_keyboard, make_keyboard};
 use std::sync::Arc;
 use teloxide::prelude::*;
 use teloxide::Bot;
-use tokio::sync::Mutex;
+
+/// Answers to "how many days a week" and "how long a session" that are accepted.
+const DAYS_PER_WEEK: std::ops::RangeInclusive<i32> = 1..=7;
+const SESSION_MINUTES: std::ops::RangeInclusive<i32> = 15..=180;
 
 pub async fn get_email(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
     log::info!("Start getting email!");
     match msg.text() {
         Some(email) => {
             if is_valid_email(email)? {
                 // process and send check to storage
-                let mut db = db.lock().await;
                 db.add_email(&phone_number, email).await?;
 
                 bot.send_message(msg.chat.id, "Дякую за пошту! \n\n Тепер відправ свій вік!")
@@ -44,25 +48,16 @@ pub async fn get_email(
 pub async fn get_number(
     bot: Bot,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     dialogue: MyDialogue,
 ) -> crate::errors::Result<()> {
-    let mut db = db.lock().await;
     // Create a list of buttons and send them.
     if let Some(contact) = msg.contact() {
         log::info!("Got contact: {:?}", contact);
         if db.if_user_exists(&contact.user_id).await? {
             log::info!("User already exists");
             bot.send_message(msg.chat.id, "Ти вже з нами!")
-                .reply_markup(
-                    make_keyboard(vec![
-                        MenuCommands::MyGymTrainings.to_string(),
-                        MenuCommands::MyHomeTrainings.to_string(),
-                        MenuCommands::MyDiet.to_string(),
-                        MenuCommands::Data.to_string(),
-                    ])
-                    .resize_keyboard(true),
-                )
+                .reply_markup(main_keyboard().resize_keyboard(true))
                 .await?;
             dialogue
                 .update(State::ChangeMenu {
@@ -95,9 +90,8 @@ pub async fn get_age(
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
-    let mut db = db.lock().await;
     log::info!("Start getting age!");
     if let Some(age) = msg.text() {
         match age.parse::<i32>() {
@@ -132,10 +126,9 @@ pub async fn get_height_and_weight(
     dialogue: MyDialogue,
     msg: Message,
     phone_number: String,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
 ) -> crate::errors::Result<()> {
     log::info!("Start getting height and weight!");
-    let mut db = db.lock().await;
     let height_and_weight: Vec<&str> = msg
         .text()
         .expect("Cannot get message value")
@@ -147,36 +140,28 @@ pub async fn get_height_and_weight(
         return Ok(());
     }
     match height_and_weight[0].parse::<i32>() {
-        Ok(height) => {
-            match height_and_weight[1].parse::<i32>() {
-                Ok(weight) => {
-                    db.add_height_and_weight(&phone_number, height, weight)
-                        .await?;
-                    // process and send check to storage
-                    bot.send_message(
-                        msg.chat.id,
-                        "Дякую за висоту та вагу! \n\n \
-                    Тепер я зможу розрахувати тренування та дієту для тебе! \n\n \
-                    Також, дякую за реєстрацію)\n\n \
-                    Ти можешь додати свої данні (обхват тіла) в меню 'Оновити данні'",
-                    )
-                    .reply_markup(
-                        make_keyboard(vec![
-                            MenuCommands::MyGymTrainings.to_string(),
-                            MenuCommands::MyHomeTrainings.to_string(),
-                            MenuCommands::MyDiet.to_string(),
-                            MenuCommands::Data.to_string(),
-                        ])
-                        .resize_keyboard(true),
-                    )
+        Ok(height) => match height_and_weight[1].parse::<i32>() {
+            Ok(weight) => {
+                db.add_height_and_weight(&phone_number, height, weight)
+                    .await?;
+                bot.send_message(
+                    msg.chat.id,
+                    "Дякую за висоту та вагу! \n\n \
+                    Ще кілька питань, щоб тренування та дієта підходили саме тобі.",
+                )
+                .await?;
+                ask_goal(&bot, msg.chat.id).await?;
+                dialogue
+                    .update(State::GetGoal {
+                        phone_number,
+                        registration: true,
+                    })
                     .await?;
-                    dialogue.update(State::ChangeMenu { phone_number }).await?;
-                }
-                Err(_) => {
-                    bot.send_message(msg.chat.id, "Вага не валідна!").await?;
-                }
             }
-        }
+            Err(_) => {
+                bot.send_message(msg.chat.id, "Вага не валідна!").await?;
+            }
+        },
         Err(_) => {
             bot.send_message(msg.chat.id, "Висота не валідна!").await?;
         }
@@ -184,3 +169,161 @@ pub async fn get_height_and_weight(
 
     Ok(())
 }
+
+pub async fn ask_goal(bot: &Bot, chat_id: ChatId) -> crate::errors::Result<()> {
+    let mut buttons: Vec<String> = Goal::ALL.iter().map(Goal::to_string).collect();
+    buttons.push(SKIP_BUTTON.to_string());
+    bot.send_message(chat_id, "Яка твоя головна мета?")
+        .reply_markup(make_column_keyboard(buttons).resize_keyboard(true))
+        .await?;
+    Ok(())
+}
+
+pub async fn get_goal(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    (phone_number, registration): (String, bool),
+    db: Arc<dyn Repository>,
+) -> crate::errors::Result<()> {
+    let text = msg.text().unwrap_or_default();
+    if text != SKIP_BUTTON {
+        let Some(goal) = Goal::from_button(text) else {
+            bot.send_message(msg.chat.id, "Обери мету кнопкою нижче!")
+                .await?;
+            return Ok(());
+        };
+        db.add_goal(&phone_number, goal.key()).await?;
+    }
+
+    let mut buttons: Vec<String> = Experience::ALL.iter().map(Experience::to_string).collect();
+    buttons.push(SKIP_BUTTON.to_string());
+    bot.send_message(msg.chat.id, "Який у тебе досвід тренувань?")
+        .reply_markup(make_column_keyboard(buttons).resize_keyboard(true))
+        .await?;
+    dialogue
+        .update(State::GetExperience {
+            phone_number,
+            registration,
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_experience(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    (phone_number, registration): (String, bool),
+    db: Arc<dyn Repository>,
+) -> crate::errors::Result<()> {
+    let text = msg.text().unwrap_or_default();
+    if text != SKIP_BUTTON {
+        let Some(experience) = Experience::from_button(text) else {
+            bot.send_message(msg.chat.id, "Обери досвід кнопкою нижче!")
+                .await?;
+            return Ok(());
+        };
+        db.add_experience(&phone_number, experience.key()).await?;
+    }
+
+    let keyboard = make_keyboard(vec![
+        "2".to_string(),
+        "3".to_string(),
+        "4".to_string(),
+        "5".to_string(),
+        SKIP_BUTTON.to_string(),
+    ]);
+    bot.send_message(msg.chat.id, "Скільки днів на тиждень ти можеш тренуватися?")
+        .reply_markup(keyboard.resize_keyboard(true))
+        .await?;
+    dialogue
+        .update(State::GetDaysPerWeek {
+            phone_number,
+            registration,
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_days_per_week(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    (phone_number, registration): (String, bool),
+    db: Arc<dyn Repository>,
+) -> crate::errors::Result<()> {
+    let text = msg.text().unwrap_or_default().trim();
+    if text != SKIP_BUTTON {
+        match text.parse::<i32>() {
+            Ok(days) if DAYS_PER_WEEK.contains(&days) => {
+                db.add_days_per_week(&phone_number, days).await?;
+            }
+            _ => {
+                bot.send_message(msg.chat.id, "Напиши число днів від 1 до 7!")
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    let keyboard = make_keyboard(vec![
+        "30".to_string(),
+        "45".to_string(),
+        "60".to_string(),
+        "90".to_string(),
+        SKIP_BUTTON.to_string(),
+    ]);
+    bot.send_message(msg.chat.id, "Скільки хвилин може тривати одне тренування?")
+        .reply_markup(keyboard.resize_keyboard(true))
+        .await?;
+    dialogue
+        .update(State::GetSessionLength {
+            phone_number,
+            registration,
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn get_session_length(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    (phone_number, registration): (String, bool),
+    db: Arc<dyn Repository>,
+) -> crate::errors::Result<()> {
+    let text = msg.text().unwrap_or_default().trim();
+    if text != SKIP_BUTTON {
+        match text.parse::<i32>() {
+            Ok(minutes) if SESSION_MINUTES.contains(&minutes) => {
+                db.add_session_minutes(&phone_number, minutes).await?;
+            }
+            _ => {
+                bot.send_message(msg.chat.id, "Напиши кількість хвилин від 15 до 180!")
+                    .await?;
+                return Ok(());
+            }
+        }
+    }
+
+    if registration {
+        bot.send_message(
+            msg.chat.id,
+            "Дякую! \n\n \
+        Тепер я зможу розрахувати тренування та дієту для тебе! \n\n \
+        Також, дякую за реєстрацію)\n\n \
+        Ти можешь додати свої данні (обхват тіла) в меню 'Оновити данні'",
+        )
+        .reply_markup(main_keyboard().resize_keyboard(true))
+        .await?;
+        dialogue.update(State::ChangeMenu { phone_number }).await?;
+    } else {
+        let keyboard = data_keyboard();
+        bot.send_message(msg.chat.id, "Дані оновлено!")
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        dialogue.update(State::Data { phone_number }).await?;
+    }
+    Ok(())
+}
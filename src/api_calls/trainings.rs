@@ -1,70 +1,60 @@
-use crate::async_openai::client::OpenAiClient;
-use crate::consts::{
-    GYM_STATE, HOME_STATE, PROMPT_MSG_GYM_TRAINING_WITHOUT_ARGS, PROMPT_MSG_GYM_TRAINING_WITH_ARGS,
-    PROMPT_MSG_HOME_TRAINING_WITHOUT_ARGS, PROMPT_MSG_HOME_TRAINING_WITH_ARGS,
-};
-use crate::db::database::Db;
-use crate::db::models::Users;
+use crate::api_calls::equipment::home_equipment;
+use crate::api_calls::live::LiveMessage;
+use crate::async_openai::metered::MeteredLlm;
+use crate::async_openai::provider::{LlmProvider, LlmProviders, Progress};
+use crate::consts::{FEATURE_TRAINING, GYM_STATE, HOME_STATE, NO_EQUIPMENT};
+use crate::db::models::{Trainings, Users};
+use crate::db::repository::Repository;
 use crate::errors::Result;
-use crate::models::{MyDialogue, State, TrainingsCommands};
-use crate::utils::{format_prompt, make_keyboard};
-use std::ops::DerefMut;
+use crate::jobs::{new_job, Jobs};
+use crate::models::{MenuCommands, MyDialogue, State, TrainingHistoryCommands, TrainingsCommands};
+use crate::plans::diff::changes_text;
+use crate::plans::training::{training_text, TrainingPlan};
+use crate::plans::{edit_plan, preview, request_plan};
+use crate::prompts::{edited_notes, profile_variables, user_notes, PromptKind, Prompts};
+use crate::quotas::Quotas;
+use crate::safety::plans::Limits;
+use crate::utils::{make_column_keyboard, make_keyboard};
 use std::sync::Arc;
 use teloxide::prelude::*;
+use teloxide::types::KeyboardMarkup;
 use teloxide::Bot;
-use tokio::sync::Mutex;
+use tokio::sync::watch;
+use uuid::Uuid;
 
 pub async fn add_training(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
-    open_ai_client: Arc<Mutex<OpenAiClient>>,
+    db: Arc<dyn Repository>,
+    jobs: Arc<Jobs>,
+    quotas: Arc<Quotas>,
     (phone_number, training_state): (String, String),
 ) -> Result<()> {
     log::info!("User {} is adding training", phone_number);
-    let mut db = db.lock().await;
     let user = db.get_user(&phone_number).await?;
 
-    let response = if training_state == HOME_STATE {
-        process_training(
-            msg.clone(),
-            open_ai_client,
-            user,
-            PROMPT_MSG_HOME_TRAINING_WITHOUT_ARGS,
-            PROMPT_MSG_HOME_TRAINING_WITH_ARGS,
-            db.deref_mut(),
-            HOME_STATE.to_string(),
-        )
+    let text = if db
+        .count_unfinished_generation_jobs(user.id, FEATURE_TRAINING)
         .await?
+        > 0
+    {
+        "Я ще складаю попереднє тренування, надішлю його, щойно воно буде готове"
+    } else if let Err(err) = quotas.check(db.as_ref(), user.id).await {
+        err.user_message()
     } else {
-        process_training(
-            msg.clone(),
-            open_ai_client,
-            user,
-            PROMPT_MSG_GYM_TRAINING_WITHOUT_ARGS,
-            PROMPT_MSG_GYM_TRAINING_WITH_ARGS,
-            db.deref_mut(),
-            GYM_STATE.to_string(),
-        )
-        .await?
+        let job = new_job(
+            user.id,
+            msg.chat.id,
+            FEATURE_TRAINING,
+            Some(training_state.clone()),
+            user_notes(msg.text()),
+        );
+        jobs.submit(job).await?;
+        "Складаю тренування, це займе хвилину-дві. Можеш далі користуватися меню, \
+         готове тренування надішлю окремим повідомленням"
     };
-
-    log::info!("Getting response for user {}", phone_number);
-
-    let keyboard = make_keyboard(vec![
-        TrainingsCommands::AddTraining.to_string(),
-        TrainingsCommands::DeleteTraining.to_string(),
-        TrainingsCommands::ShowTrainings.to_string(),
-        TrainingsCommands::GoBack.to_string(),
-    ]);
-
-    bot.send_message(
-        msg.chat.id,
-        format!("Тренування додано! \n\n А ось і воно: \n\n {}", response),
-    )
-    .reply_markup(keyboard.resize_keyboard(true))
-    .await?;
+    bot.send_message(msg.chat.id, text).await?;
 
     if training_state == HOME_STATE {
         dialogue
@@ -79,15 +69,98 @@ pub async fn add_training(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
+pub async fn edit_training(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    llms: Arc<LlmProviders>,
+    quotas: Arc<Quotas>,
+    prompts: Arc<Prompts>,
+    (phone_number, training_state): (String, String),
+) -> Result<()> {
+    let Some(request) = msg.text() else {
+        bot.send_message(msg.chat.id, "Напиши текстом, що змінити!")
+            .await?;
+        return Ok(());
+    };
+    log::info!("User {} is editing training", phone_number);
+    let user = db.get_user(&phone_number).await?;
+
+    match db.get_training(user.id, training_state.clone()).await {
+        Ok(training) => {
+            let notes = edited_notes(training.notes.as_deref(), request);
+            let llm = MeteredLlm::new(
+                llms.get(FEATURE_TRAINING),
+                db.as_ref(),
+                user.id,
+                FEATURE_TRAINING,
+            );
+            let live = LiveMessage::start(&bot, msg.chat.id, "Змінюю тренування...").await?;
+            let (progress, answer) = watch::channel(String::new());
+            let plan = live
+                .track(answer, preview, async {
+                    quotas.check(db.as_ref(), user.id).await?;
+                    let mut limits = Limits::for_user(db.as_ref(), &user, Some(&notes)).await?;
+                    if training_state == HOME_STATE {
+                        let equipment = home_equipment(db.as_ref(), user.id).await?;
+                        limits = limits.with_equipment(equipment);
+                    }
+                    edit_plan::<TrainingPlan>(
+                        &llm,
+                        &prompts,
+                        &training.user_trainings,
+                        request,
+                        &limits,
+                        &progress,
+                    )
+                    .await
+                })
+                .await;
+            let text = match plan {
+                Ok(plan) => {
+                    let before = training_text(&training.user_trainings)?;
+                    let after = plan.to_string();
+                    db.update_training(
+                        user.id,
+                        serde_json::to_value(&plan)?,
+                        training_state.clone(),
+                        Some(notes),
+                    )
+                    .await?;
+                    format!(
+                        "Тренування оновлено! {} \n\n А ось і воно: \n\n {}",
+                        changes_text(&before, &after),
+                        after
+                    )
+                }
+                Err(err) => {
+                    log::error!("Cannot edit training for user {}: {}", phone_number, err);
+                    err.user_message().to_string()
+                }
+            };
+            live.finish(text).await?;
+        }
+        Err(_) => {
+            bot.send_message(msg.chat.id, "Тренування відсутнє!")
+                .await?;
+        }
+    }
+    dialogue
+        .update(training_menu_state(phone_number, training_state))
+        .await?;
+    Ok(())
+}
+
 pub async fn show_trainings(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     (phone_number, training_state): (String, String),
 ) -> Result<()> {
     log::info!("User {} is showing training", phone_number);
-    let mut db = db.lock().await;
     let user = db.get_user(&phone_number).await?;
     let user_id = user.id;
     let trainings = if training_state == HOME_STATE {
@@ -98,14 +171,9 @@ pub async fn show_trainings(
 
     match trainings {
         Ok(trainings) => {
-            let keyboard = make_keyboard(vec![
-                TrainingsCommands::AddTraining.to_string(),
-                TrainingsCommands::DeleteTraining.to_string(),
-                TrainingsCommands::ShowTrainings.to_string(),
-                TrainingsCommands::GoBack.to_string(),
-            ]);
+            let keyboard = training_keyboard(&training_state);
 
-            let trainings: String = serde_json::from_value(trainings.user_trainings)?;
+            let trainings = training_text(&trainings.user_trainings)?;
 
             bot.send_message(
                 msg.chat.id,
@@ -118,12 +186,7 @@ pub async fn show_trainings(
                 .await?;
         }
         Err(_) => {
-            let keyboard = make_keyboard(vec![
-                TrainingsCommands::AddTraining.to_string(),
-                TrainingsCommands::DeleteTraining.to_string(),
-                TrainingsCommands::ShowTrainings.to_string(),
-                TrainingsCommands::GoBack.to_string(),
-            ]);
+            let keyboard = training_keyboard(&training_state);
 
             bot.send_message(msg.chat.id, "Тренування відсутнє!".to_string())
                 .reply_markup(keyboard.resize_keyboard(true))
@@ -140,11 +203,10 @@ pub async fn delete_training(
     bot: Bot,
     dialogue: MyDialogue,
     msg: Message,
-    db: Arc<Mutex<Db>>,
+    db: Arc<dyn Repository>,
     (phone_number, training_status): (String, String),
 ) -> Result<()> {
     log::info!("User {} is deleting training", phone_number);
-    let mut db = db.lock().await;
     let user = db.get_user(&phone_number).await?;
     let user_id = user.id;
 
@@ -156,12 +218,7 @@ pub async fn delete_training(
 
     match result {
         Ok(_) => {
-            let keyboard = make_keyboard(vec![
-                TrainingsCommands::AddTraining.to_string(),
-                TrainingsCommands::DeleteTraining.to_string(),
-                TrainingsCommands::ShowTrainings.to_string(),
-                TrainingsCommands::GoBack.to_string(),
-            ]);
+            let keyboard = training_keyboard(&training_status);
 
             bot.send_message(msg.chat.id, "Тренування видалено!")
                 .reply_markup(keyboard.resize_keyboard(true))
@@ -178,12 +235,7 @@ pub async fn delete_training(
             }
         }
         Err(_) => {
-            let keyboard = make_keyboard(vec![
-                TrainingsCommands::AddTraining.to_string(),
-                TrainingsCommands::DeleteTraining.to_string(),
-                TrainingsCommands::ShowTrainings.to_string(),
-                TrainingsCommands::GoBack.to_string(),
-            ]);
+            let keyboard = training_keyboard(&training_status);
 
             bot.send_message(msg.chat.id, "Тренування вже відсутнє!")
                 .reply_markup(keyboard.resize_keyboard(true))
@@ -203,26 +255,238 @@ pub async fn delete_training(
     Ok(())
 }
 
-pub async fn process_training(
+pub async fn show_training_history(
+    bot: Bot,
+    dialogue: MyDialogue,
     msg: Message,
-    open_ai_client: Arc<Mutex<OpenAiClient>>,
+    db: Arc<dyn Repository>,
+    (phone_number, training_state): (String, String),
+) -> Result<()> {
+    log::info!("User {} is showing training history", phone_number);
+    let user = db.get_user(&phone_number).await?;
+    let trainings = db.get_trainings(user.id, training_state.clone()).await?;
+
+    if trainings.is_empty() {
+        let keyboard = training_keyboard(&training_state);
+
+        bot.send_message(msg.chat.id, "Тренування відсутнє!")
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+        dialogue
+            .update(training_menu_state(phone_number, training_state))
+            .await?;
+        return Ok(());
+    }
+
+    let mut buttons: Vec<String> = trainings
+        .iter()
+        .enumerate()
+        .map(|(i, training)| history_button(i, training))
+        .collect();
+    buttons.push(TrainingHistoryCommands::GoBack.to_string());
+
+    bot.send_message(
+        msg.chat.id,
+        "Обери тренування, яке хочеш переглянути. Перше у списку - активне.",
+    )
+    .reply_markup(make_column_keyboard(buttons).resize_keyboard(true))
+    .await?;
+    dialogue
+        .update(State::TrainingHistory {
+            phone_number,
+            training_state,
+        })
+        .await?;
+    Ok(())
+}
+
+pub async fn choose_training_version(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    (phone_number, training_state): (String, String),
+) -> Result<()> {
+    let Some(text) = msg.text() else {
+        bot.send_message(msg.chat.id, "Обери тренування зі списку!")
+            .await?;
+        return Ok(());
+    };
+
+    if text == TrainingHistoryCommands::GoBack.to_string() {
+        return go_to_training_menu(bot, dialogue, msg, phone_number, training_state).await;
+    }
+
+    let user = db.get_user(&phone_number).await?;
+    let trainings = db.get_trainings(user.id, training_state.clone()).await?;
+    let training = text
+        .split('.')
+        .next()
+        .and_then(|number| number.trim().parse::<usize>().ok())
+        .and_then(|number| number.checked_sub(1))
+        .and_then(|index| trainings.get(index));
+
+    match training {
+        Some(training) => {
+            log::info!("User {} is showing training {}", phone_number, training.id);
+            let keyboard = make_keyboard(vec![
+                TrainingHistoryCommands::Restore.to_string(),
+                TrainingHistoryCommands::GoBack.to_string(),
+            ]);
+
+            let text = training_text(&training.user_trainings)?;
+
+            bot.send_message(
+                msg.chat.id,
+                format!(
+                    "Тренування від {}: \n\n {}",
+                    training.created_at.format("%d.%m.%Y %H:%M"),
+                    text
+                ),
+            )
+            .reply_markup(keyboard.resize_keyboard(true))
+            .await?;
+            dialogue
+                .update(State::TrainingVersion {
+                    phone_number,
+                    training_state,
+                    training_id: training.id,
+                })
+                .await?;
+        }
+        None => {
+            bot.send_message(msg.chat.id, "Такого тренування немає!")
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+pub async fn training_version_menu(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    db: Arc<dyn Repository>,
+    (phone_number, training_state, training_id): (String, String, Uuid),
+) -> Result<()> {
+    let command = TrainingHistoryCommands::from(msg.text().unwrap_or_default().to_string());
+    match command {
+        TrainingHistoryCommands::Restore => {
+            log::info!(
+                "User {} is restoring training {}",
+                phone_number,
+                training_id
+            );
+            let user = db.get_user(&phone_number).await?;
+            db.restore_training(user.id, training_id).await?;
+
+            bot.send_message(msg.chat.id, "Тренування відновлено, тепер воно активне!")
+                .await?;
+            go_to_training_menu(bot, dialogue, msg, phone_number, training_state).await?;
+        }
+        TrainingHistoryCommands::GoBack => {
+            show_training_history(bot, dialogue, msg, db, (phone_number, training_state)).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn go_to_training_menu(
+    bot: Bot,
+    dialogue: MyDialogue,
+    msg: Message,
+    phone_number: String,
+    training_state: String,
+) -> Result<()> {
+    let keyboard = training_keyboard(&training_state);
+
+    let title = if training_state == HOME_STATE {
+        MenuCommands::MyHomeTrainings.to_string()
+    } else {
+        MenuCommands::MyGymTrainings.to_string()
+    };
+
+    bot.send_message(msg.chat.id, title)
+        .reply_markup(keyboard.resize_keyboard(true))
+        .await?;
+    dialogue
+        .update(training_menu_state(phone_number, training_state))
+        .await?;
+    Ok(())
+}
+
+/// Buttons of the training menu, the home one also opens the equipment inventory.
+pub fn training_keyboard(training_state: &str) -> KeyboardMarkup {
+    let mut buttons = vec![
+        TrainingsCommands::AddTraining.to_string(),
+        TrainingsCommands::EditTraining.to_string(),
+        TrainingsCommands::DeleteTraining.to_string(),
+        TrainingsCommands::ShowTrainings.to_string(),
+        TrainingsCommands::History.to_string(),
+        TrainingsCommands::GoBack.to_string(),
+    ];
+    if training_state == HOME_STATE {
+        buttons.insert(5, TrainingsCommands::Equipment.to_string());
+    }
+    make_keyboard(buttons)
+}
+
+fn training_menu_state(phone_number: String, training_state: String) -> State {
+    if training_state == HOME_STATE {
+        State::HomeTrainingMenu { phone_number }
+    } else {
+        State::GymTrainingMenu { phone_number }
+    }
+}
+
+fn history_button(index: usize, training: &Trainings) -> String {
+    let button = format!(
+        "{}. {}",
+        index + 1,
+        training.created_at.format("%d.%m.%Y %H:%M")
+    );
+    if index == 0 {
+        format!("{} (активне)", button)
+    } else {
+        button
+    }
+}
+
+pub async fn process_training(
+    notes: Option<&str>,
+    llm: &dyn LlmProvider,
+    prompts: &Prompts,
     user: Users,
-    const1: &str,
-    const2: &str,
-    db: &mut Db,
+    db: &dyn Repository,
     status: String,
-) -> Result<String> {
-    let response = if let Some(text) = msg.text() {
-        let prompt = format_prompt(Some(text), const1, const2, user.clone());
-        log::info!("Start sending prompt for training {}!", prompt);
-        open_ai_client.lock().await.send_message(&prompt).await?
+    progress: &Progress,
+) -> Result<TrainingPlan> {
+    let mut variables = profile_variables(db, &user).await?;
+    let mut limits = Limits::for_user(db, &user, notes).await?;
+    let kind = if status == HOME_STATE {
+        let equipment = home_equipment(db, user.id).await?;
+        let text = if equipment.is_empty() {
+            NO_EQUIPMENT.to_string()
+        } else {
+            equipment.join(", ")
+        };
+        variables.insert("equipment", text);
+        limits = limits.with_equipment(equipment);
+        PromptKind::HomeTraining
     } else {
-        let prompt = format_prompt(None, const1, const2, user.clone());
-        log::info!("Start sending prompt for training {}!", prompt);
-        open_ai_client.lock().await.send_message(&prompt).await?
+        PromptKind::GymTraining
     };
+    let prompt = prompts.render(kind, &variables);
+    log::info!("Start sending prompt for training {}!", prompt);
+    let response = request_plan::<TrainingPlan>(llm, &prompt, notes, &limits, progress).await?;
 
     let user_id = user.id;
-    db.insert_training(user_id, &response, status).await?;
+    db.insert_training(
+        user_id,
+        serde_json::to_value(&response)?,
+        status,
+        notes.map(str::to_string),
+    )
+    .await?;
     Ok(response)
 }
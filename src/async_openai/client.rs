@@ -1,42 +1,520 @@
-use crate::consts::OPEN_AI_MODEL;
-use crate::errors::Result;
-use async_openai::config::OpenAIConfig;
+use crate::async_openai::config::ModelSettings;
+use crate::async_openai::provider::{
+    ChatMessage, ChatRole, Completion, LlmProvider, Progress, TokenUsage,
+};
+use crate::errors::{Errors, Result};
+use async_openai::config::{Config, OpenAIConfig};
+use async_openai::error::OpenAIError;
 use async_openai::types::{
-    ChatCompletionRequestSystemMessageArgs, CreateChatCompletionRequestArgs,
+    ChatChoiceStream, ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessageArgs, ChatCompletionRequestUserMessageArgs, CompletionUsage,
+    CreateChatCompletionRequest, CreateChatCompletionRequestArgs, CreateChatCompletionResponse,
+    FinishReason,
 };
 use async_openai::Client;
+use async_trait::async_trait;
+use backoff::ExponentialBackoffBuilder;
+use futures::StreamExt;
+use reqwest_eventsource::{Event, EventSource};
+use serde::Deserialize;
+use serde_json::json;
+use std::future::Future;
+use std::time::Duration;
+
+/// How long a single completion may take before it is abandoned.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// How long a streamed completion may wait for its next chunk.
+const CHUNK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Attempts per prompt, the first one included.
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Pause before the first retry, doubled before every next one.
+const RETRY_DELAY: Duration = Duration::from_secs(2);
 
+/// A [`LlmProvider`] for the OpenAI API or any server speaking the same protocol.
 pub struct OpenAiClient {
     pub client: Client<OpenAIConfig>,
+    /// Sends the streamed requests, async-openai can't ask for their usage.
+    http: reqwest::Client,
+    settings: ModelSettings,
+}
+
+/// A chunk of a streamed answer. With `include_usage` the last chunk has no
+/// choices and carries the usage of the whole answer.
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    choices: Vec<ChatChoiceStream>,
+    usage: Option<CompletionUsage>,
 }
 
 impl OpenAiClient {
-    pub fn new(api_key: String) -> Self {
-        let config = OpenAIConfig::default().with_api_key(api_key);
-        let client = Client::with_config(config);
-        Self { client }
-    }
-
-    pub async fn send_message(&self, text: &str) -> Result<String> {
-        let request = CreateChatCompletionRequestArgs::default()
-            .model(OPEN_AI_MODEL)
-            .messages([ChatCompletionRequestSystemMessageArgs::default()
-                .content(text)
-                .build()?
-                .into()])
-            .build()?;
-
-        // Call API
-        let response = self
-            .client
-            .chat() // Get the API "group" (completions, images, etc.) from the client
-            .create(request) // Make the API call in that "group"
-            .await?;
-
-        Ok(response.choices[0]
-            .message
-            .content
-            .clone()
-            .expect("Can't get content"))
+    /// Without `api_base` the requests go to the OpenAI API.
+    pub fn new(api_key: String, api_base: Option<String>, settings: ModelSettings) -> Self {
+        let mut config = OpenAIConfig::default().with_api_key(api_key);
+        if let Some(api_base) = api_base {
+            config = config.with_api_base(api_base);
+        }
+        // Rate limits are retried by `with_retries`, the client would wait up to 15 minutes.
+        let backoff = ExponentialBackoffBuilder::new()
+            .with_max_elapsed_time(Some(Duration::ZERO))
+            .build();
+        let http = reqwest::Client::new();
+        let client = Client::with_config(config)
+            .with_http_client(http.clone())
+            .with_backoff(backoff);
+        Self {
+            client,
+            http,
+            settings,
+        }
+    }
+
+    async fn complete(&self, request: CreateChatCompletionRequest) -> Result<Completion> {
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, self.client.chat().create(request))
+            .await
+            .map_err(|_| Errors::LlmTimeout)?
+            .map_err(classify_error)?;
+        let usage = response.usage.as_ref().map(token_usage).unwrap_or_default();
+        Ok(Completion {
+            content: answer_text(response)?,
+            usage,
+        })
+    }
+
+    /// Reads the answer chunk by chunk. The usage comes in the last chunk, it
+    /// is estimated when the server doesn't send it.
+    async fn complete_stream(
+        &self,
+        request: CreateChatCompletionRequest,
+        messages: &[ChatMessage],
+        progress: &Progress,
+    ) -> Result<Completion> {
+        let mut stream = self.open_stream(request)?;
+        let mut content = String::new();
+        let mut finish_reason = None;
+        let mut usage = None;
+        while let Some(event) = tokio::time::timeout(CHUNK_TIMEOUT, stream.next())
+            .await
+            .map_err(|_| Errors::LlmTimeout)?
+        {
+            let data = match event {
+                Ok(Event::Open) => continue,
+                Ok(Event::Message(message)) if message.data == "[DONE]" => break,
+                Ok(Event::Message(message)) => message.data,
+                Err(err) => return Err(classify_error(OpenAIError::StreamError(err.to_string()))),
+            };
+            let chunk: StreamChunk = serde_json::from_str(&data)
+                .map_err(|err| classify_error(OpenAIError::JSONDeserialize(err)))?;
+            usage = chunk.usage.as_ref().map(token_usage).or(usage);
+            let Some(choice) = chunk.choices.into_iter().find(|choice| choice.index == 0) else {
+                continue;
+            };
+            if let Some(delta) = choice.delta.content {
+                content.push_str(&delta);
+                progress.send_replace(content.clone());
+            }
+            finish_reason = choice.finish_reason.or(finish_reason);
+        }
+        stream.close();
+        if finish_reason == Some(FinishReason::ContentFilter) {
+            return Err(Errors::ContentFiltered);
+        }
+        if content.trim().is_empty() {
+            return Err(Errors::EmptyResponse);
+        }
+        Ok(Completion {
+            usage: usage.unwrap_or_else(|| TokenUsage::estimate(messages, &content)),
+            content,
+        })
+    }
+
+    /// Starts a streamed completion that ends with the usage of the answer.
+    fn open_stream(&self, request: CreateChatCompletionRequest) -> Result<EventSource> {
+        let mut body = serde_json::to_value(request)?;
+        body["stream"] = json!(true);
+        body["stream_options"] = json!({ "include_usage": true });
+        let config = self.client.config();
+        let request = self
+            .http
+            .post(config.url("/chat/completions"))
+            .query(&config.query())
+            .headers(config.headers())
+            .json(&body);
+        EventSource::new(request)
+            .map_err(|err| Errors::OpenAIError(OpenAIError::StreamError(err.to_string())))
+    }
+
+    fn request(&self, messages: &[ChatMessage]) -> Result<CreateChatCompletionRequest> {
+        let system_prompt = self
+            .settings
+            .system_prompt
+            .as_ref()
+            .map(|prompt| ChatMessage::new(ChatRole::System, prompt.as_str()));
+        let messages = system_prompt
+            .iter()
+            .chain(messages)
+            .map(request_message)
+            .collect::<Result<Vec<_>>>()?;
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model(&self.settings.model).messages(messages);
+        if let Some(temperature) = self.settings.temperature {
+            request.temperature(temperature);
+        }
+        if let Some(max_tokens) = self.settings.max_tokens {
+            request.max_tokens(max_tokens);
+        }
+        Ok(request.build()?)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiClient {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<Completion> {
+        let request = self.request(messages)?;
+        with_retries(|| self.complete(request.clone())).await
+    }
+
+    async fn chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        progress: &Progress,
+    ) -> Result<Completion> {
+        let request = self.request(messages)?;
+        with_retries(|| self.complete_stream(request.clone(), messages, progress)).await
+    }
+}
+
+/// Repeats the completion while it fails with a transient error.
+async fn with_retries<F, Fut>(mut complete: F) -> Result<Completion>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Completion>>,
+{
+    let mut delay = RETRY_DELAY;
+    let mut attempt = 1;
+    loop {
+        match complete().await {
+            Err(err) if attempt < MAX_ATTEMPTS && is_transient(&err) => {
+                log::warn!(
+                    "Completion attempt {} of {} failed, retrying in {:?}: {}",
+                    attempt,
+                    MAX_ATTEMPTS,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn request_message(message: &ChatMessage) -> Result<ChatCompletionRequestMessage> {
+    let content = message.content.as_str();
+    Ok(match message.role {
+        ChatRole::System => ChatCompletionRequestSystemMessageArgs::default()
+            .content(content)
+            .build()?
+            .into(),
+        ChatRole::User => ChatCompletionRequestUserMessageArgs::default()
+            .content(content)
+            .build()?
+            .into(),
+        ChatRole::Assistant => ChatCompletionRequestAssistantMessageArgs::default()
+            .content(content)
+            .build()?
+            .into(),
+    })
+}
+
+fn token_usage(usage: &CompletionUsage) -> TokenUsage {
+    TokenUsage {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+    }
+}
+
+/// Takes the text of the first choice, a missing or filtered one is an error.
+fn answer_text(response: CreateChatCompletionResponse) -> Result<String> {
+    let choice = response
+        .choices
+        .into_iter()
+        .next()
+        .ok_or(Errors::EmptyResponse)?;
+    if choice.finish_reason == Some(FinishReason::ContentFilter) {
+        return Err(Errors::ContentFiltered);
+    }
+    match choice.message.content {
+        Some(content) if !content.trim().is_empty() => Ok(content),
+        _ => Err(Errors::EmptyResponse),
+    }
+}
+
+/// Turns the API errors that need their own handling into typed variants.
+fn classify_error(err: OpenAIError) -> Errors {
+    if let OpenAIError::ApiError(api_error) = &err {
+        let kind = api_error.r#type.as_deref().unwrap_or_default();
+        let code = api_error
+            .code
+            .as_ref()
+            .and_then(|code| code.as_str())
+            .unwrap_or_default();
+        if code == "rate_limit_exceeded" || kind == "requests" {
+            return Errors::RateLimited;
+        }
+        if code == "content_filter" || code == "content_policy_violation" {
+            return Errors::ContentFiltered;
+        }
+    }
+    // Streams report HTTP errors only as text, e.g. "Invalid status code: 429 Too Many Requests".
+    if let OpenAIError::StreamError(message) = &err {
+        if message.contains("429") {
+            return Errors::RateLimited;
+        }
+    }
+    Errors::OpenAIError(err)
+}
+
+/// Whether another attempt may succeed: rate limits, timeouts, empty answers,
+/// network failures and 5xx responses, which carry no JSON error object.
+fn is_transient(err: &Errors) -> bool {
+    match err {
+        Errors::RateLimited | Errors::LlmTimeout | Errors::EmptyResponse => true,
+        Errors::OpenAIError(OpenAIError::Reqwest(_) | OpenAIError::JSONDeserialize(_)) => true,
+        Errors::OpenAIError(OpenAIError::ApiError(api_error)) => {
+            api_error.r#type.as_deref() == Some("server_error")
+        }
+        Errors::OpenAIError(OpenAIError::StreamError(message)) => {
+            !message.starts_with("Invalid status code: 4")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_openai::error::ApiError;
+    use std::cell::Cell;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    const CHUNKS: [&str; 2] = [
+        r#"{"choices": [{"index": 0, "delta": {"role": "assistant", "content": "При"}}]}"#,
+        r#"{"choices": [{"index": 0, "delta": {"content": "віт"}, "finish_reason": "stop"}]}"#,
+    ];
+
+    const USAGE_CHUNK: &str = r#"{"choices": [], "usage": {"prompt_tokens": 7, "completion_tokens": 2, "total_tokens": 9}}"#;
+
+    /// Answers one request with the server-sent `events`, the handle returns the request.
+    async fn serve(events: Vec<&'static str>) -> Result<(String, JoinHandle<Result<String>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/v1", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await?;
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            // The JSON body comes last.
+            while !request.ends_with(b"}") {
+                let read = socket.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buffer[..read]);
+            }
+            let body: String = events
+                .iter()
+                .map(|event| format!("data: {}\n\n", event))
+                .collect();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await?;
+            Ok(String::from_utf8_lossy(&request).into_owned())
+        });
+        Ok((url, server))
+    }
+
+    fn client(url: String) -> OpenAiClient {
+        let settings = ModelSettings {
+            model: "gpt".to_string(),
+            temperature: None,
+            max_tokens: None,
+            system_prompt: None,
+        };
+        OpenAiClient::new("key".to_string(), Some(url), settings)
+    }
+
+    fn api_error(kind: Option<&str>, code: Option<&str>) -> OpenAIError {
+        OpenAIError::ApiError(ApiError {
+            message: "error".to_string(),
+            r#type: kind.map(str::to_string),
+            param: None,
+            code: code.map(serde_json::Value::from),
+        })
+    }
+
+    fn response(choices: &str) -> serde_json::Result<CreateChatCompletionResponse> {
+        serde_json::from_str(&format!(
+            r#"{{"id": "1", "object": "chat.completion", "created": 0, "model": "gpt", "choices": {}}}"#,
+            choices
+        ))
+    }
+
+    fn completion() -> Completion {
+        Completion {
+            content: "ok".to_string(),
+            usage: TokenUsage::default(),
+        }
+    }
+
+    #[test]
+    fn classifies_api_errors() {
+        let rate_limit = api_error(Some("requests"), Some("rate_limit_exceeded"));
+        assert!(matches!(classify_error(rate_limit), Errors::RateLimited));
+        let filtered = api_error(Some("invalid_request_error"), Some("content_filter"));
+        assert!(matches!(classify_error(filtered), Errors::ContentFiltered));
+        let stream = OpenAIError::StreamError("Invalid status code: 429 Too Many Requests".into());
+        assert!(matches!(classify_error(stream), Errors::RateLimited));
+        let invalid = api_error(Some("invalid_request_error"), Some("invalid_api_key"));
+        assert!(matches!(classify_error(invalid), Errors::OpenAIError(_)));
+    }
+
+    #[test]
+    fn retries_only_transient_errors() {
+        let transient = [
+            Errors::RateLimited,
+            Errors::LlmTimeout,
+            Errors::EmptyResponse,
+            Errors::OpenAIError(api_error(Some("server_error"), None)),
+            Errors::OpenAIError(OpenAIError::StreamError(
+                "Invalid status code: 503 Service Unavailable".into(),
+            )),
+        ];
+        for err in &transient {
+            assert!(is_transient(err), "{}", err);
+        }
+        let permanent = [
+            Errors::ContentFiltered,
+            Errors::OpenAIError(api_error(Some("invalid_request_error"), None)),
+            Errors::OpenAIError(OpenAIError::StreamError(
+                "Invalid status code: 401 Unauthorized".into(),
+            )),
+            Errors::OpenAIError(OpenAIError::InvalidArgument("model".into())),
+        ];
+        for err in &permanent {
+            assert!(!is_transient(err), "{}", err);
+        }
+    }
+
+    #[test]
+    fn empty_or_filtered_answers_are_errors() -> serde_json::Result<()> {
+        assert!(matches!(
+            answer_text(response("[]")?),
+            Err(Errors::EmptyResponse)
+        ));
+        let blank = r#"[{"index": 0, "message": {"role": "assistant", "content": "  "}, "finish_reason": "stop"}]"#;
+        assert!(matches!(
+            answer_text(response(blank)?),
+            Err(Errors::EmptyResponse)
+        ));
+        let filtered = r#"[{"index": 0, "message": {"role": "assistant", "content": "..."}, "finish_reason": "content_filter"}]"#;
+        assert!(matches!(
+            answer_text(response(filtered)?),
+            Err(Errors::ContentFiltered)
+        ));
+        let answer = r#"[{"index": 0, "message": {"role": "assistant", "content": "Привіт"}, "finish_reason": "stop"}]"#;
+        assert_eq!(
+            answer_text(response(answer)?).ok().as_deref(),
+            Some("Привіт")
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_until_success() {
+        let attempts = Cell::new(0);
+        let result = with_retries(|| {
+            attempts.set(attempts.get() + 1);
+            let attempt = attempts.get();
+            async move {
+                if attempt < MAX_ATTEMPTS {
+                    Err(Errors::RateLimited)
+                } else {
+                    Ok(completion())
+                }
+            }
+        })
+        .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts.get(), MAX_ATTEMPTS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_attempts() {
+        let attempts = Cell::new(0);
+        let result = with_retries(|| {
+            attempts.set(attempts.get() + 1);
+            async { Err(Errors::LlmTimeout) }
+        })
+        .await;
+        assert!(matches!(result, Err(Errors::LlmTimeout)));
+        assert_eq!(attempts.get(), MAX_ATTEMPTS);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_retry_permanent_errors() {
+        let attempts = Cell::new(0);
+        let result = with_retries(|| {
+            attempts.set(attempts.get() + 1);
+            async { Err(Errors::ContentFiltered) }
+        })
+        .await;
+        assert!(matches!(result, Err(Errors::ContentFiltered)));
+        assert_eq!(attempts.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn streamed_answer_reports_its_usage() -> Result<()> {
+        let (url, server) = serve([&CHUNKS[..], &[USAGE_CHUNK, "[DONE]"]].concat()).await?;
+        let (progress, answer) = tokio::sync::watch::channel(String::new());
+        let messages = [ChatMessage::new(ChatRole::User, "Привіт")];
+
+        let completion = client(url).chat_stream(&messages, &progress).await?;
+
+        assert!(server
+            .await??
+            .contains(r#""stream_options":{"include_usage":true}"#));
+        assert_eq!(completion.content, "Привіт");
+        assert_eq!(*answer.borrow(), "Привіт");
+        assert_eq!(
+            completion.usage,
+            TokenUsage {
+                prompt_tokens: 7,
+                completion_tokens: 2
+            }
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn estimates_usage_when_stream_has_none() -> Result<()> {
+        let (url, server) = serve([&CHUNKS[..], &["[DONE]"]].concat()).await?;
+        let (progress, _answer) = tokio::sync::watch::channel(String::new());
+        let messages = [ChatMessage::new(ChatRole::User, "Привіт")];
+
+        let completion = client(url).chat_stream(&messages, &progress).await?;
+
+        server.await??;
+        assert_eq!(completion.usage, TokenUsage::estimate(&messages, "Привіт"));
+        Ok(())
     }
 }
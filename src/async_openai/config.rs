@@ -0,0 +1,38 @@
+use crate::consts::OPEN_AI_MODEL;
+use crate::errors::Result;
+
+/// How the model answers for one feature.
+#[derive(Clone, Debug)]
+pub struct ModelSettings {
+    pub model: String,
+    /// `None` keeps the default of the API.
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u16>,
+    /// Sent before every conversation of the feature.
+    pub system_prompt: Option<String>,
+}
+
+impl ModelSettings {
+    /// Reads `LLM_MODEL`, `LLM_TEMPERATURE`, `LLM_MAX_TOKENS` and `LLM_SYSTEM_PROMPT`,
+    /// each of them can be overridden for a single feature, e.g. `LLM_TRAINING_MODEL`.
+    pub fn from_env(feature: Option<&str>) -> Result<Self> {
+        let var = |name: &str| {
+            feature
+                .and_then(|feature| {
+                    dotenv::var(format!("LLM_{}_{}", feature.to_uppercase(), name)).ok()
+                })
+                .or_else(|| dotenv::var(format!("LLM_{}", name)).ok())
+                .filter(|value| !value.trim().is_empty())
+        };
+        Ok(Self {
+            model: var("MODEL").unwrap_or_else(|| OPEN_AI_MODEL.to_string()),
+            temperature: var("TEMPERATURE")
+                .map(|temperature| temperature.trim().parse())
+                .transpose()?,
+            max_tokens: var("MAX_TOKENS")
+                .map(|max_tokens| max_tokens.trim().parse())
+                .transpose()?,
+            system_prompt: var("SYSTEM_PROMPT"),
+        })
+    }
+}
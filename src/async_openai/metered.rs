@@ -0,0 +1,154 @@
+use crate::async_openai::provider::{ChatMessage, Completion, LlmProvider, Progress, TokenUsage};
+use crate::db::repository::Repository;
+use crate::errors::Result;
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Wraps a [`LlmProvider`] and stores the tokens of every completion
+/// as spent by `user_id` on `feature`.
+pub struct MeteredLlm<'a> {
+    llm: &'a dyn LlmProvider,
+    db: &'a dyn Repository,
+    user_id: Uuid,
+    feature: &'static str,
+}
+
+impl<'a> MeteredLlm<'a> {
+    pub fn new(
+        llm: &'a dyn LlmProvider,
+        db: &'a dyn Repository,
+        user_id: Uuid,
+        feature: &'static str,
+    ) -> Self {
+        Self {
+            llm,
+            db,
+            user_id,
+            feature,
+        }
+    }
+
+    async fn record(&self, usage: TokenUsage) -> Result<()> {
+        log::info!(
+            "User {} spent {} prompt and {} completion tokens on {}",
+            self.user_id,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+            self.feature
+        );
+        self.db
+            .add_token_usage(
+                self.user_id,
+                self.feature,
+                usage.prompt_tokens as i32,
+                usage.completion_tokens as i32,
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MeteredLlm<'_> {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<Completion> {
+        let completion = self.llm.chat(messages).await?;
+        self.record(completion.usage).await?;
+        Ok(completion)
+    }
+
+    async fn chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        progress: &Progress,
+    ) -> Result<Completion> {
+        let completion = self.llm.chat_stream(messages, progress).await?;
+        self.record(completion.usage).await?;
+        Ok(completion)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_openai::mock::MockProvider;
+    use crate::async_openai::provider::ChatRole;
+    use crate::db::in_memory::InMemoryDb;
+    use crate::errors::Errors;
+    use chrono::{DateTime, Utc};
+
+    /// Streams one answer with the usage the server reported for it.
+    struct StreamedProvider;
+
+    #[async_trait]
+    impl LlmProvider for StreamedProvider {
+        async fn chat(&self, _messages: &[ChatMessage]) -> Result<Completion> {
+            Err(Errors::EmptyResponse)
+        }
+
+        async fn chat_stream(
+            &self,
+            _messages: &[ChatMessage],
+            progress: &Progress,
+        ) -> Result<Completion> {
+            progress.send_replace("Присідай".to_string());
+            Ok(Completion {
+                content: "Присідай".to_string(),
+                usage: TokenUsage {
+                    prompt_tokens: 1200,
+                    completion_tokens: 800,
+                },
+            })
+        }
+    }
+
+    async fn stored_usage(db: &InMemoryDb, user_id: Uuid) -> Result<Vec<TokenUsage>> {
+        Ok(db
+            .get_token_usage_since(DateTime::<Utc>::MIN_UTC)
+            .await?
+            .into_iter()
+            .filter(|usage| usage.user_id == user_id && usage.feature == "training")
+            .map(|usage| TokenUsage {
+                prompt_tokens: usage.prompt_tokens as u32,
+                completion_tokens: usage.completion_tokens as u32,
+            })
+            .collect())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stores_usage_of_every_completion() -> Result<()> {
+        let db = InMemoryDb::new();
+        let mock = MockProvider::scripted(["Присідай", "Бігай"]);
+        let user_id = Uuid::new_v4();
+        let llm = MeteredLlm::new(&mock, &db, user_id, "training");
+        let messages = [ChatMessage::new(ChatRole::User, "Як тренуватись?")];
+        let (progress, _answer) = tokio::sync::watch::channel(String::new());
+
+        let streamed = llm.chat_stream(&messages, &progress).await?;
+        let answered = llm.chat(&messages).await?;
+
+        assert_eq!(
+            stored_usage(&db, user_id).await?,
+            [streamed.usage, answered.usage]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stores_usage_reported_by_stream() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user_id = Uuid::new_v4();
+        let llm = MeteredLlm::new(&StreamedProvider, &db, user_id, "training");
+        let messages = [ChatMessage::new(ChatRole::User, "Як тренуватись?")];
+        let (progress, _answer) = tokio::sync::watch::channel(String::new());
+
+        llm.chat_stream(&messages, &progress).await?;
+
+        assert_eq!(
+            stored_usage(&db, user_id).await?,
+            [TokenUsage {
+                prompt_tokens: 1200,
+                completion_tokens: 800
+            }]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use crate::async_openai::provider::{
+    ChatMessage, ChatRole, Completion, LlmProvider, Progress, TokenUsage,
+};
+use crate::errors::Result;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Characters added to the answer with every streamed chunk.
+const CHUNK_CHARS: usize = 40;
+
+/// Pause between streamed chunks, so the mock streams like a real model.
+const CHUNK_DELAY: Duration = Duration::from_millis(100);
+
+/// Answer to prompts asking for a training plan as JSON.
+const MOCK_TRAINING_PLAN: &str = r#"{"days": [
+{"day": "Понеділок", "focus": "Все тіло", "duration_minutes": 45, "exercises": [
+{"name": "Присідання", "sets": 3, "reps": "12", "rest_seconds": 60, "equipment": null},
+{"name": "Віджимання", "sets": 3, "reps": "10", "rest_seconds": 60, "equipment": null}]},
+{"day": "Середа", "focus": "Спина та кор", "duration_minutes": 40, "exercises": [
+{"name": "Тяга гантелі в нахилі", "sets": 3, "reps": "10", "rest_seconds": 90, "equipment": "Гантелі"},
+{"name": "Планка", "sets": 3, "reps": "60 с", "rest_seconds": 45, "equipment": null}]},
+{"day": "П'ятниця", "focus": "Ноги", "duration_minutes": 45, "exercises": [
+{"name": "Випади", "sets": 3, "reps": "12", "rest_seconds": 60, "equipment": null}]}],
+"notes": "Тестовий розклад, краще узгодити його з тренером."}"#;
+
+/// Answer to prompts asking for a diet plan as JSON.
+const MOCK_DIET_PLAN: &str = r#"{"days": [
+{"day": "Понеділок", "meals": [
+{"name": "Сніданок", "foods": [
+{"name": "Вівсянка на воді", "grams": 80, "kcal": 290, "protein": 10, "fat": 5, "carbs": 50},
+{"name": "Яйця варені", "grams": 120, "kcal": 186, "protein": 15, "fat": 13, "carbs": 1}]},
+{"name": "Обід", "foods": [
+{"name": "Куряча грудка", "grams": 150, "kcal": 165, "protein": 35, "fat": 2, "carbs": 0},
+{"name": "Гречка", "grams": 200, "kcal": 220, "protein": 8, "fat": 2, "carbs": 42},
+{"name": "Хліб цільнозерновий", "grams": 60, "kcal": 150, "protein": 6, "fat": 2, "carbs": 26},
+{"name": "Оливкова олія", "grams": 15, "kcal": 135, "protein": 0, "fat": 15, "carbs": 0}]},
+{"name": "Перекус", "foods": [
+{"name": "Банан", "grams": 120, "kcal": 107, "protein": 1, "fat": 0, "carbs": 27},
+{"name": "Волоські горіхи", "grams": 30, "kcal": 196, "protein": 5, "fat": 20, "carbs": 4}]},
+{"name": "Вечеря", "foods": [
+{"name": "Сир кисломолочний 5%", "grams": 200, "kcal": 242, "protein": 34, "fat": 10, "carbs": 6}]}]}],
+"notes": "Тестова дієта, краще узгодити її з дієтологом."}"#;
+
+/// A [`LlmProvider`] that answers without any network call, the same prompt
+/// always gets the same answer.
+#[derive(Default)]
+pub struct MockProvider {
+    /// Answers given in order before the usual ones, tests use them to script a conversation.
+    script: Mutex<VecDeque<String>>,
+    /// Every conversation the mock was asked to answer.
+    #[cfg(test)]
+    requests: Mutex<Vec<Vec<ChatMessage>>>,
+}
+
+impl MockProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A mock that gives `answers` first, one per request.
+    #[cfg(test)]
+    pub fn scripted<'a>(answers: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            script: Mutex::new(answers.into_iter().map(str::to_string).collect()),
+            ..Self::default()
+        }
+    }
+
+    /// The conversations the mock received, oldest first.
+    #[cfg(test)]
+    pub fn requests(&self) -> Vec<Vec<ChatMessage>> {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn next_answer(&self, messages: &[ChatMessage]) -> String {
+        #[cfg(test)]
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(messages.to_vec());
+        self.script
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
+            .unwrap_or_else(|| answer(messages))
+    }
+}
+
+fn answer(messages: &[ChatMessage]) -> String {
+    let prompt = messages
+        .first()
+        .map(|message| message.content.as_str())
+        .unwrap_or_default();
+    if prompt.contains("\"exercises\"") {
+        return MOCK_TRAINING_PLAN.to_string();
+    }
+    if prompt.contains("\"meals\"") {
+        return MOCK_DIET_PLAN.to_string();
+    }
+
+    let question = messages
+        .iter()
+        .rev()
+        .find(|message| message.role == ChatRole::User);
+    if let Some(question) = question {
+        return format!(
+            "Тестова відповідь тренера на \"{}\" ({} повідомлень у розмові)",
+            question.content,
+            messages.len()
+        );
+    }
+    format!(
+        "Тестовий розклад. \n\n\
+         Понеділок: присідання 3x12, віджимання 3x10 \n\
+         Середа: тяга 3x10, планка 3x60 с \n\
+         П'ятниця: випади 3x12, підтягування 3x8 \n\n\
+         Запит: {}",
+        prompt
+    )
+}
+
+#[async_trait]
+impl LlmProvider for MockProvider {
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<Completion> {
+        let content = self.next_answer(messages);
+        log::info!("Mock provider answers {} messages", messages.len());
+        Ok(Completion {
+            usage: TokenUsage::estimate(messages, &content),
+            content,
+        })
+    }
+
+    async fn chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        progress: &Progress,
+    ) -> Result<Completion> {
+        let content = self.next_answer(messages);
+        log::info!(
+            "Mock provider streams answer to {} messages",
+            messages.len()
+        );
+        let chars: Vec<char> = content.chars().collect();
+        for chunk in 1..=chars.len().div_ceil(CHUNK_CHARS) {
+            tokio::time::sleep(CHUNK_DELAY).await;
+            let end = (chunk * CHUNK_CHARS).min(chars.len());
+            progress.send_replace(chars[..end].iter().collect());
+        }
+        Ok(Completion {
+            usage: TokenUsage::estimate(messages, &content),
+            content,
+        })
+    }
+}
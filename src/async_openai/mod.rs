@@ -1 +1,5 @@
 pub mod client;
+pub mod config;
+pub mod metered;
+pub mod mock;
+pub mod provider;
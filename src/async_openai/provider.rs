@@ -0,0 +1,102 @@
+use crate::consts::FEATURES;
+use crate::errors::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Who a message of a conversation comes from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+/// Tokens spent on a completion.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+}
+
+impl TokenUsage {
+    /// Rough count, about four characters per token, for answers that come without usage.
+    pub fn estimate(messages: &[ChatMessage], answer: &str) -> Self {
+        let tokens = |text: &str| (text.chars().count() / 4) as u32 + 1;
+        Self {
+            prompt_tokens: messages
+                .iter()
+                .map(|message| tokens(&message.content))
+                .sum(),
+            completion_tokens: tokens(answer),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    pub content: String,
+    pub usage: TokenUsage,
+}
+
+/// Holds the text of the answer received so far while it is streamed.
+pub type Progress = tokio::sync::watch::Sender<String>;
+
+/// A language model the plans are generated with. Handlers get it as
+/// `Arc<dyn LlmProvider>`, so the backend is picked once at startup.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Sends the conversation and returns the text of the next answer.
+    async fn chat(&self, messages: &[ChatMessage]) -> Result<Completion>;
+
+    /// Like [`LlmProvider::chat`], but publishes the answer to `progress` as it is generated.
+    /// Providers that can't stream publish the whole answer at once.
+    async fn chat_stream(
+        &self,
+        messages: &[ChatMessage],
+        progress: &Progress,
+    ) -> Result<Completion> {
+        let completion = self.chat(messages).await?;
+        progress.send_replace(completion.content.clone());
+        Ok(completion)
+    }
+}
+
+/// The language model of every feature, each one may use its own model and settings.
+pub struct LlmProviders {
+    default: Arc<dyn LlmProvider>,
+    features: HashMap<&'static str, Arc<dyn LlmProvider>>,
+}
+
+impl LlmProviders {
+    /// Creates the provider of every feature in [`FEATURES`] and the default one with `make`.
+    pub fn new(make: impl Fn(Option<&str>) -> Result<Arc<dyn LlmProvider>>) -> Result<Self> {
+        let features = FEATURES
+            .into_iter()
+            .map(|feature| Ok((feature, make(Some(feature))?)))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            default: make(None)?,
+            features,
+        })
+    }
+
+    pub fn get(&self, feature: &str) -> &dyn LlmProvider {
+        self.features.get(feature).unwrap_or(&self.default).as_ref()
+    }
+}
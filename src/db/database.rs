@@ -1,24 +1,121 @@
-use crate::db::models::{DietLists, Sizes, Trainings, Users};
+use crate::consts::{COACH_SUMMARY_ROLE, JOB_QUEUED, JOB_RUNNING};
+use crate::db::models::{
+    CoachMessages, Dialogues, DietLists, GenerationJobs, HomeEquipment, Sizes, TokenUsages,
+    Trainings, Users, Weights,
+};
+use crate::db::repository::Repository;
 use crate::errors::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use diesel::dsl::sql;
+use diesel::expression::SqlLiteral;
 use diesel::prelude::*;
-use diesel::{Connection, PgConnection};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::sql_types::Timestamptz;
+use diesel::PgConnection;
 use serde_json::Value;
 use teloxide::prelude::UserId;
 use uuid::Uuid;
 
+pub type DbPool = Pool<ConnectionManager<PgConnection>>;
+
+/// Default amount of connections kept in the pool.
+const DEFAULT_POOL_SIZE: u32 = 10;
+
 pub struct Db {
-    pub conn: PgConnection,
+    pub pool: DbPool,
 }
 
 impl Db {
-    pub fn new(database_url: &str) -> Self {
-        let conn = PgConnection::establish(database_url)
-            .unwrap_or_else(|_| panic!("Error connecting to {}", database_url));
-        Self { conn }
+    pub fn new(database_url: &str, pool_size: Option<u32>) -> Result<Self> {
+        let manager = ConnectionManager::<PgConnection>::new(database_url);
+        let pool = Pool::builder()
+            .max_size(pool_size.unwrap_or(DEFAULT_POOL_SIZE))
+            .build(manager)?;
+        Ok(Self { pool })
+    }
+
+    /// Runs a blocking diesel query on a pooled connection without blocking the async runtime.
+    pub(crate) async fn run<F, T>(&self, query: F) -> Result<T>
+    where
+        F: FnOnce(&mut PgConnection) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = pool.get()?;
+            query(&mut conn)
+        })
+        .await?
+    }
+
+    pub async fn get_dialogue(&self, chat_id: i64) -> Result<Option<Value>> {
+        self.run(move |conn| {
+            let state = crate::db::schema::dialogues::table
+                .select(crate::db::schema::dialogues::state)
+                .filter(crate::db::schema::dialogues::chat_id.eq(chat_id))
+                .first(conn)
+                .optional()?;
+            Ok(state)
+        })
+        .await
+    }
+
+    pub async fn update_dialogue(&self, chat_id: i64, state: Value) -> Result<()> {
+        let dialogue = Dialogues {
+            chat_id,
+            state,
+            updated_at: chrono::Utc::now(),
+        };
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::dialogues::table)
+                .values(&dialogue)
+                .on_conflict(crate::db::schema::dialogues::chat_id)
+                .do_update()
+                .set(&dialogue)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn remove_dialogue(&self, chat_id: i64) -> Result<()> {
+        self.run(move |conn| {
+            let removed = diesel::delete(crate::db::schema::dialogues::table)
+                .filter(crate::db::schema::dialogues::chat_id.eq(chat_id))
+                .execute(conn)?;
+            if removed == 0 {
+                return Err(diesel::result::Error::NotFound.into());
+            }
+            Ok(())
+        })
+        .await
     }
+}
+
+/// The active training plan of a location is the one created or restored last.
+fn training_activity() -> SqlLiteral<Timestamptz> {
+    sql::<Timestamptz>("COALESCE(trainings.updated_at, trainings.created_at)")
+}
 
-    pub async fn insert_user(
-        &mut self,
+/// Records a new weigh-in, the newest one is the current weight of the user.
+fn insert_weight(conn: &mut PgConnection, user_id: Uuid, weight: i32) -> Result<()> {
+    let new_weight = Weights {
+        id: Uuid::new_v4(),
+        user_id,
+        weight,
+        measured_at: chrono::Utc::now(),
+    };
+    diesel::insert_into(crate::db::schema::weights::table)
+        .values(&new_weight)
+        .execute(conn)?;
+    Ok(())
+}
+
+#[async_trait]
+impl Repository for Db {
+    async fn insert_user(
+        &self,
         name: &str,
         phone_number: &str,
         telegram_id: &Option<UserId>,
@@ -31,199 +128,396 @@ impl Db {
             email: None,
             phone_number: phone_number.to_string(),
             height: None,
-            weight: None,
             age: None,
+            goal: None,
+            experience: None,
+            days_per_week: None,
+            session_minutes: None,
         };
-        diesel::insert_into(crate::db::schema::users::table)
-            .values(&new_user)
-            .execute(&mut self.conn)?;
-        Ok(())
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::users::table)
+                .values(&new_user)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_email(&self, phone_number: &str, email: &str) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        let email = email.to_string();
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .set(crate::db::schema::users::email.eq(email))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_age(&self, phone_number: &str, age: i32) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .set(crate::db::schema::users::age.eq(age))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_goal(&self, phone_number: &str, goal: &str) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        let goal = goal.to_string();
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .set(crate::db::schema::users::goal.eq(goal))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_experience(&self, phone_number: &str, experience: &str) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        let experience = experience.to_string();
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .set(crate::db::schema::users::experience.eq(experience))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn add_email(&mut self, phone_number: &str, email: &str) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::users::table)
-            .filter(crate::db::schema::users::phone_number.eq(phone_number.to_string()))
-            .set(crate::db::schema::users::email.eq(email.to_string()))
-            .execute(&mut self.conn)?;
-        Ok(())
+    async fn add_days_per_week(&self, phone_number: &str, days: i32) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .set(crate::db::schema::users::days_per_week.eq(days))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn add_age(&mut self, phone_number: &str, age: i32) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::users::table)
-            .filter(crate::db::schema::users::phone_number.eq(phone_number.to_string()))
-            .set(crate::db::schema::users::age.eq(age))
-            .execute(&mut self.conn)?;
-        Ok(())
+    async fn add_session_minutes(&self, phone_number: &str, minutes: i32) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .set(crate::db::schema::users::session_minutes.eq(minutes))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn add_height_and_weight(
-        &mut self,
+    async fn add_height_and_weight(
+        &self,
         phone_number: &str,
         height: i32,
         weight: i32,
     ) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::users::table)
-            .filter(crate::db::schema::users::phone_number.eq(phone_number.to_string()))
-            .set((
-                crate::db::schema::users::height.eq(height),
-                crate::db::schema::users::weight.eq(weight),
-            ))
-            .execute(&mut self.conn)?;
-        Ok(())
+        let phone_number = phone_number.to_string();
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let user_id = diesel::update(crate::db::schema::users::table)
+                    .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                    .set(crate::db::schema::users::height.eq(height))
+                    .returning(crate::db::schema::users::id)
+                    .get_result(conn)?;
+                insert_weight(conn, user_id, weight)
+            })
+        })
+        .await
     }
 
-    pub async fn get_user(&mut self, phone_number: &str) -> Result<Users> {
+    async fn get_user(&self, phone_number: &str) -> Result<Users> {
         log::info!("Getting user with phone number {}", phone_number);
-        let user = crate::db::schema::users::table
-            .filter(crate::db::schema::users::phone_number.eq(phone_number))
-            .get_result(&mut self.conn)?;
-        Ok(user)
-    }
-
-    pub async fn if_user_exists(&mut self, phone_number: &Option<UserId>) -> Result<bool> {
-        let user: Option<String> = crate::db::schema::users::table
-            .select(crate::db::schema::users::phone_number)
-            .filter(
-                crate::db::schema::users::telegram_id
-                    .eq(phone_number.unwrap_or(UserId(0)).0 as i32),
-            )
-            .first(&mut self.conn)
-            .optional()?;
-        Ok(user.is_some())
-    }
-
-    #[allow(dead_code)]
-    pub async fn get_user_by_id(&mut self, id: Uuid) -> Result<Users> {
-        let user = crate::db::schema::users::table
-            .filter(crate::db::schema::users::id.eq(id))
-            .first(&mut self.conn)?;
-        Ok(user)
-    }
-
-    #[allow(dead_code)]
-    pub async fn delete_user(&mut self, phone_number: &str) -> Result<()> {
-        diesel::delete(crate::db::schema::users::table)
-            .filter(crate::db::schema::users::phone_number.eq(phone_number.to_string()))
-            .execute(&mut self.conn)?;
-        Ok(())
-    }
-
-    pub async fn insert_training(
-        &mut self,
+        let phone_number = phone_number.to_string();
+        self.run(move |conn| {
+            let user = crate::db::schema::users::table
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .get_result(conn)?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn if_user_exists(&self, phone_number: &Option<UserId>) -> Result<bool> {
+        let telegram_id = phone_number.unwrap_or(UserId(0)).0 as i32;
+        self.run(move |conn| {
+            let user: Option<String> = crate::db::schema::users::table
+                .select(crate::db::schema::users::phone_number)
+                .filter(crate::db::schema::users::telegram_id.eq(telegram_id))
+                .first(conn)
+                .optional()?;
+            Ok(user.is_some())
+        })
+        .await
+    }
+
+    async fn get_user_by_telegram_id(&self, telegram_id: &Option<UserId>) -> Result<Option<Users>> {
+        let telegram_id = telegram_id.unwrap_or(UserId(0)).0 as i32;
+        self.run(move |conn| {
+            let user = crate::db::schema::users::table
+                .filter(crate::db::schema::users::telegram_id.eq(telegram_id))
+                .first(conn)
+                .optional()?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn get_user_by_id(&self, id: Uuid) -> Result<Users> {
+        self.run(move |conn| {
+            let user = crate::db::schema::users::table
+                .filter(crate::db::schema::users::id.eq(id))
+                .first(conn)?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn delete_user(&self, phone_number: &str) -> Result<()> {
+        let phone_number = phone_number.to_string();
+        self.run(move |conn| {
+            diesel::delete(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::phone_number.eq(phone_number))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn insert_training(
+        &self,
         user_id: Uuid,
-        training: &str,
+        training: Value,
         status: String,
+        notes: Option<String>,
     ) -> Result<()> {
         log::info!("Inserting gym training for user {}", user_id);
         let id = Uuid::new_v4();
         let new_gym_training = Trainings {
             id,
             user_id,
-            user_trainings: serde_json::to_value(training)?,
+            user_trainings: training,
             status,
             created_at: chrono::Utc::now(),
             updated_at: None,
+            notes,
         };
-        diesel::insert_into(crate::db::schema::trainings::table)
-            .values(&new_gym_training)
-            .execute(&mut self.conn)?;
-        Ok(())
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::trainings::table)
+                .values(&new_gym_training)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn get_training(&mut self, user_id: Uuid, status: String) -> Result<Trainings> {
-        let home_training = crate::db::schema::trainings::table
-            .filter(crate::db::schema::trainings::user_id.eq(user_id))
-            .filter(crate::db::schema::trainings::status.eq(status))
-            .first(&mut self.conn)?;
-        Ok(home_training)
+    async fn get_training(&self, user_id: Uuid, status: String) -> Result<Trainings> {
+        self.run(move |conn| {
+            let home_training = crate::db::schema::trainings::table
+                .filter(crate::db::schema::trainings::user_id.eq(user_id))
+                .filter(crate::db::schema::trainings::status.eq(status))
+                .order(training_activity().desc())
+                .first(conn)?;
+            Ok(home_training)
+        })
+        .await
     }
 
-    #[allow(dead_code)]
-    pub async fn update_training(
-        &mut self,
+    async fn get_trainings(&self, user_id: Uuid, status: String) -> Result<Vec<Trainings>> {
+        self.run(move |conn| {
+            let trainings = crate::db::schema::trainings::table
+                .filter(crate::db::schema::trainings::user_id.eq(user_id))
+                .filter(crate::db::schema::trainings::status.eq(status))
+                .order(training_activity().desc())
+                .load(conn)?;
+            Ok(trainings)
+        })
+        .await
+    }
+
+    async fn restore_training(&self, user_id: Uuid, id: Uuid) -> Result<()> {
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::trainings::table)
+                .filter(crate::db::schema::trainings::user_id.eq(user_id))
+                .filter(crate::db::schema::trainings::id.eq(id))
+                .set(crate::db::schema::trainings::updated_at.eq(chrono::Utc::now()))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_training(
+        &self,
         user_id: Uuid,
         training: Value,
         status: String,
+        notes: Option<String>,
     ) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::trainings::table)
-            .filter(crate::db::schema::trainings::user_id.eq(user_id))
-            .filter(crate::db::schema::trainings::status.eq(status))
-            .set(crate::db::schema::trainings::user_trainings.eq(training))
-            .execute(&mut self.conn)?;
-        Ok(())
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let id: Uuid = crate::db::schema::trainings::table
+                    .select(crate::db::schema::trainings::id)
+                    .filter(crate::db::schema::trainings::user_id.eq(user_id))
+                    .filter(crate::db::schema::trainings::status.eq(status))
+                    .order(training_activity().desc())
+                    .first(conn)?;
+                let _ = diesel::update(crate::db::schema::trainings::table)
+                    .filter(crate::db::schema::trainings::id.eq(id))
+                    .set((
+                        crate::db::schema::trainings::user_trainings.eq(training),
+                        crate::db::schema::trainings::updated_at.eq(chrono::Utc::now()),
+                        crate::db::schema::trainings::notes.eq(notes),
+                    ))
+                    .execute(conn)?;
+                Ok(())
+            })
+        })
+        .await
     }
 
-    pub async fn delete_training(&mut self, user_id: Uuid, status: String) -> Result<()> {
-        diesel::delete(crate::db::schema::trainings::table)
-            .filter(crate::db::schema::trainings::user_id.eq(user_id))
-            .filter(crate::db::schema::trainings::status.eq(status))
-            .execute(&mut self.conn)?;
-        Ok(())
+    async fn delete_training(&self, user_id: Uuid, status: String) -> Result<()> {
+        self.run(move |conn| {
+            diesel::delete(crate::db::schema::trainings::table)
+                .filter(crate::db::schema::trainings::user_id.eq(user_id))
+                .filter(crate::db::schema::trainings::status.eq(status))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn insert_diet_list(&mut self, user_id: Uuid, diet_list: &str) -> Result<()> {
+    async fn insert_diet_list(
+        &self,
+        user_id: Uuid,
+        diet_list: Value,
+        notes: Option<String>,
+    ) -> Result<()> {
         let id = Uuid::new_v4();
         let new_diet_list = DietLists {
             id,
             user_id,
-            diet_list: serde_json::to_value(diet_list)?,
+            diet_list,
             created_at: chrono::Utc::now(),
             updated_at: None,
+            notes,
         };
-        diesel::insert_into(crate::db::schema::diet_lists::table)
-            .values(&new_diet_list)
-            .execute(&mut self.conn)?;
-        Ok(())
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::diet_lists::table)
+                .values(&new_diet_list)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn get_diet_list(&mut self, user_id: Uuid) -> Result<DietLists> {
-        let diet_list = crate::db::schema::diet_lists::table
-            .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
-            .first(&mut self.conn)?;
-        Ok(diet_list)
+    async fn get_diet_list(&self, user_id: Uuid) -> Result<DietLists> {
+        self.run(move |conn| {
+            let diet_list = crate::db::schema::diet_lists::table
+                .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
+                .order(crate::db::schema::diet_lists::created_at.desc())
+                .first(conn)?;
+            Ok(diet_list)
+        })
+        .await
     }
 
-    #[allow(dead_code)]
-    pub async fn update_diet_list(&mut self, user_id: Uuid, diet_list: Value) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::diet_lists::table)
-            .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
-            .set(crate::db::schema::diet_lists::diet_list.eq(diet_list))
-            .execute(&mut self.conn)?;
-        Ok(())
+    async fn get_diet_lists(&self, user_id: Uuid) -> Result<Vec<DietLists>> {
+        self.run(move |conn| {
+            let diet_lists = crate::db::schema::diet_lists::table
+                .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
+                .order(crate::db::schema::diet_lists::created_at.asc())
+                .load(conn)?;
+            Ok(diet_lists)
+        })
+        .await
     }
 
-    pub async fn delete_diet_list(&mut self, user_id: Uuid) -> Result<()> {
-        diesel::delete(crate::db::schema::diet_lists::table)
-            .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
-            .execute(&mut self.conn)?;
-        Ok(())
+    async fn update_diet_list(
+        &self,
+        user_id: Uuid,
+        diet_list: Value,
+        notes: Option<String>,
+    ) -> Result<()> {
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let id: Uuid = crate::db::schema::diet_lists::table
+                    .select(crate::db::schema::diet_lists::id)
+                    .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
+                    .order(crate::db::schema::diet_lists::created_at.desc())
+                    .first(conn)?;
+                let _ = diesel::update(crate::db::schema::diet_lists::table)
+                    .filter(crate::db::schema::diet_lists::id.eq(id))
+                    .set((
+                        crate::db::schema::diet_lists::diet_list.eq(diet_list),
+                        crate::db::schema::diet_lists::updated_at.eq(chrono::Utc::now()),
+                        crate::db::schema::diet_lists::notes.eq(notes),
+                    ))
+                    .execute(conn)?;
+                diesel::QueryResult::Ok(())
+            })?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_diet_list(&self, user_id: Uuid) -> Result<()> {
+        self.run(move |conn| {
+            diesel::delete(crate::db::schema::diet_lists::table)
+                .filter(crate::db::schema::diet_lists::user_id.eq(user_id))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn update_age(&mut self, user_id: Uuid, age: i32) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::users::table)
-            .filter(crate::db::schema::users::id.eq(user_id))
-            .set(crate::db::schema::users::age.eq(age))
-            .execute(&mut self.conn)?;
-        Ok(())
+    async fn update_age(&self, user_id: Uuid, age: i32) -> Result<()> {
+        self.run(move |conn| {
+            let _ = diesel::update(crate::db::schema::users::table)
+                .filter(crate::db::schema::users::id.eq(user_id))
+                .set(crate::db::schema::users::age.eq(age))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 
-    pub async fn update_height_and_weight(
-        &mut self,
+    async fn update_height_and_weight(
+        &self,
         user_id: Uuid,
         height: i32,
         weight: i32,
     ) -> Result<()> {
-        let _ = diesel::update(crate::db::schema::users::table)
-            .filter(crate::db::schema::users::id.eq(user_id))
-            .set((
-                crate::db::schema::users::height.eq(height),
-                crate::db::schema::users::weight.eq(weight),
-            ))
-            .execute(&mut self.conn)?;
-        Ok(())
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                let _ = diesel::update(crate::db::schema::users::table)
+                    .filter(crate::db::schema::users::id.eq(user_id))
+                    .set(crate::db::schema::users::height.eq(height))
+                    .execute(conn)?;
+                insert_weight(conn, user_id, weight)
+            })
+        })
+        .await
     }
 
-    pub async fn update_size(&mut self, user_id: Uuid, size: Vec<String>) -> Result<()> {
+    async fn update_size(
+        &self,
+        user_id: Uuid,
+        size: Vec<String>,
+        measured_at: DateTime<Utc>,
+    ) -> Result<()> {
         let chest = size[0].parse()?;
         let waist = size[1].parse()?;
         let hips = size[2].parse()?;
@@ -240,26 +534,294 @@ impl Db {
             hand_biceps,
             leg_biceps,
             calf,
+            measured_at,
         };
-        let _ = diesel::insert_into(crate::db::schema::sizes::table)
-            .values(&sizes)
-            .execute(&mut self.conn)?;
-        Ok(())
-    }
-
-    pub async fn get_size_by_user(&mut self, user_id: Uuid) -> Result<Option<Sizes>> {
-        let user = crate::db::schema::sizes::table
-            .filter(crate::db::schema::sizes::user_id.eq(user_id))
-            .first(&mut self.conn)
-            .optional()?;
-        Ok(user)
-    }
-
-    pub async fn get_sizes_by_user(&mut self, user_id: Uuid) -> Result<Option<Vec<Sizes>>> {
-        let user = crate::db::schema::sizes::table
-            .filter(crate::db::schema::sizes::user_id.eq(user_id))
-            .load(&mut self.conn)
-            .optional()?;
-        Ok(user)
+        self.run(move |conn| {
+            let _ = diesel::insert_into(crate::db::schema::sizes::table)
+                .values(&sizes)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_size_by_user(&self, user_id: Uuid) -> Result<Option<Sizes>> {
+        self.run(move |conn| {
+            let user = crate::db::schema::sizes::table
+                .filter(crate::db::schema::sizes::user_id.eq(user_id))
+                .order(crate::db::schema::sizes::measured_at.desc())
+                .first(conn)
+                .optional()?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn get_sizes_by_user(&self, user_id: Uuid) -> Result<Option<Vec<Sizes>>> {
+        self.run(move |conn| {
+            let user = crate::db::schema::sizes::table
+                .filter(crate::db::schema::sizes::user_id.eq(user_id))
+                .order(crate::db::schema::sizes::measured_at.asc())
+                .load(conn)
+                .optional()?;
+            Ok(user)
+        })
+        .await
+    }
+
+    async fn add_weight(&self, user_id: Uuid, weight: i32) -> Result<()> {
+        self.run(move |conn| insert_weight(conn, user_id, weight))
+            .await
+    }
+
+    async fn get_latest_weight(&self, user_id: Uuid) -> Result<Option<Weights>> {
+        self.run(move |conn| {
+            let weight = crate::db::schema::weights::table
+                .filter(crate::db::schema::weights::user_id.eq(user_id))
+                .order(crate::db::schema::weights::measured_at.desc())
+                .first(conn)
+                .optional()?;
+            Ok(weight)
+        })
+        .await
+    }
+
+    async fn get_weights_by_user(&self, user_id: Uuid) -> Result<Vec<Weights>> {
+        self.run(move |conn| {
+            let weights = crate::db::schema::weights::table
+                .filter(crate::db::schema::weights::user_id.eq(user_id))
+                .order(crate::db::schema::weights::measured_at.asc())
+                .load(conn)?;
+            Ok(weights)
+        })
+        .await
+    }
+
+    async fn add_coach_message(&self, user_id: Uuid, role: &str, content: &str) -> Result<()> {
+        let message = CoachMessages {
+            id: Uuid::new_v4(),
+            user_id,
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::coach_messages::table)
+                .values(&message)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_coach_messages(&self, user_id: Uuid) -> Result<Vec<CoachMessages>> {
+        self.run(move |conn| {
+            let messages = crate::db::schema::coach_messages::table
+                .filter(crate::db::schema::coach_messages::user_id.eq(user_id))
+                .order(crate::db::schema::coach_messages::created_at.asc())
+                .load(conn)?;
+            Ok(messages)
+        })
+        .await
+    }
+
+    async fn summarize_coach_messages(
+        &self,
+        user_id: Uuid,
+        until: DateTime<Utc>,
+        summary: &str,
+    ) -> Result<()> {
+        let summary = CoachMessages {
+            id: Uuid::new_v4(),
+            user_id,
+            role: COACH_SUMMARY_ROLE.to_string(),
+            content: summary.to_string(),
+            created_at: until,
+        };
+        self.run(move |conn| {
+            conn.transaction(|conn| {
+                diesel::delete(crate::db::schema::coach_messages::table)
+                    .filter(crate::db::schema::coach_messages::user_id.eq(user_id))
+                    .filter(crate::db::schema::coach_messages::created_at.le(until))
+                    .execute(conn)?;
+                diesel::insert_into(crate::db::schema::coach_messages::table)
+                    .values(&summary)
+                    .execute(conn)?;
+                diesel::QueryResult::Ok(())
+            })?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_coach_messages(&self, user_id: Uuid) -> Result<()> {
+        self.run(move |conn| {
+            diesel::delete(crate::db::schema::coach_messages::table)
+                .filter(crate::db::schema::coach_messages::user_id.eq(user_id))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn add_token_usage(
+        &self,
+        user_id: Uuid,
+        feature: &str,
+        prompt_tokens: i32,
+        completion_tokens: i32,
+    ) -> Result<()> {
+        let usage = TokenUsages {
+            id: Uuid::new_v4(),
+            user_id,
+            feature: feature.to_string(),
+            prompt_tokens,
+            completion_tokens,
+            created_at: chrono::Utc::now(),
+        };
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::token_usage::table)
+                .values(&usage)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_tokens_used(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<i64> {
+        use crate::db::schema::token_usage::dsl::{
+            completion_tokens, created_at, prompt_tokens, token_usage, user_id as usage_user_id,
+        };
+        self.run(move |conn| {
+            let tokens: Option<i64> = token_usage
+                .select(diesel::dsl::sum(prompt_tokens + completion_tokens))
+                .filter(usage_user_id.eq(user_id))
+                .filter(created_at.ge(since))
+                .first(conn)?;
+            Ok(tokens.unwrap_or(0))
+        })
+        .await
+    }
+
+    async fn get_token_usage_since(&self, since: DateTime<Utc>) -> Result<Vec<TokenUsages>> {
+        self.run(move |conn| {
+            let usage = crate::db::schema::token_usage::table
+                .filter(crate::db::schema::token_usage::created_at.ge(since))
+                .order(crate::db::schema::token_usage::created_at.asc())
+                .load(conn)?;
+            Ok(usage)
+        })
+        .await
+    }
+
+    async fn add_generation_job(&self, job: &GenerationJobs) -> Result<()> {
+        let job = job.clone();
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::generation_jobs::table)
+                .values(&job)
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_unfinished_generation_jobs(&self) -> Result<Vec<GenerationJobs>> {
+        self.run(move |conn| {
+            let jobs = crate::db::schema::generation_jobs::table
+                .filter(crate::db::schema::generation_jobs::state.eq_any([JOB_QUEUED, JOB_RUNNING]))
+                .order(crate::db::schema::generation_jobs::created_at.asc())
+                .load(conn)?;
+            Ok(jobs)
+        })
+        .await
+    }
+
+    async fn count_unfinished_generation_jobs(&self, user_id: Uuid, feature: &str) -> Result<i64> {
+        let feature = feature.to_string();
+        self.run(move |conn| {
+            let count = crate::db::schema::generation_jobs::table
+                .filter(crate::db::schema::generation_jobs::user_id.eq(user_id))
+                .filter(crate::db::schema::generation_jobs::feature.eq(feature))
+                .filter(crate::db::schema::generation_jobs::state.eq_any([JOB_QUEUED, JOB_RUNNING]))
+                .count()
+                .get_result(conn)?;
+            Ok(count)
+        })
+        .await
+    }
+
+    async fn get_home_equipment(&self, user_id: Uuid) -> Result<Vec<HomeEquipment>> {
+        self.run(move |conn| {
+            let equipment = crate::db::schema::home_equipment::table
+                .filter(crate::db::schema::home_equipment::user_id.eq(user_id))
+                .order(crate::db::schema::home_equipment::created_at.asc())
+                .load(conn)?;
+            Ok(equipment)
+        })
+        .await
+    }
+
+    async fn add_home_equipment(&self, user_id: Uuid, name: &str) -> Result<()> {
+        let item = HomeEquipment {
+            id: Uuid::new_v4(),
+            user_id,
+            name: name.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        self.run(move |conn| {
+            diesel::insert_into(crate::db::schema::home_equipment::table)
+                .values(&item)
+                .on_conflict((
+                    crate::db::schema::home_equipment::user_id,
+                    crate::db::schema::home_equipment::name,
+                ))
+                .do_nothing()
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn delete_home_equipment(&self, user_id: Uuid, name: &str) -> Result<()> {
+        let name = name.to_string();
+        self.run(move |conn| {
+            diesel::delete(crate::db::schema::home_equipment::table)
+                .filter(crate::db::schema::home_equipment::user_id.eq(user_id))
+                .filter(crate::db::schema::home_equipment::name.eq(name))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn clear_home_equipment(&self, user_id: Uuid) -> Result<()> {
+        self.run(move |conn| {
+            diesel::delete(crate::db::schema::home_equipment::table)
+                .filter(crate::db::schema::home_equipment::user_id.eq(user_id))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_generation_job(
+        &self,
+        id: Uuid,
+        state: &str,
+        error: Option<String>,
+    ) -> Result<()> {
+        let state = state.to_string();
+        self.run(move |conn| {
+            diesel::update(crate::db::schema::generation_jobs::table)
+                .filter(crate::db::schema::generation_jobs::id.eq(id))
+                .set((
+                    crate::db::schema::generation_jobs::state.eq(state),
+                    crate::db::schema::generation_jobs::error.eq(error),
+                    crate::db::schema::generation_jobs::updated_at.eq(chrono::Utc::now()),
+                ))
+                .execute(conn)?;
+            Ok(())
+        })
+        .await
     }
 }
@@ -0,0 +1,776 @@
+use crate::consts::{COACH_SUMMARY_ROLE, JOB_QUEUED, JOB_RUNNING};
+use crate::db::models::{
+    CoachMessages, DietLists, GenerationJobs, HomeEquipment, Sizes, TokenUsages, Trainings, Users,
+    Weights,
+};
+use crate::db::repository::Repository;
+use crate::errors::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use teloxide::prelude::UserId;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Default)]
+struct Tables {
+    users: Vec<Users>,
+    trainings: Vec<Trainings>,
+    diet_lists: Vec<DietLists>,
+    sizes: Vec<Sizes>,
+    weights: Vec<Weights>,
+    coach_messages: Vec<CoachMessages>,
+    token_usage: Vec<TokenUsages>,
+    generation_jobs: Vec<GenerationJobs>,
+    home_equipment: Vec<HomeEquipment>,
+}
+
+/// A [`Repository`] that keeps every row in memory, everything is lost after a restart.
+#[derive(Default)]
+pub struct InMemoryDb {
+    tables: Mutex<Tables>,
+}
+
+impl InMemoryDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn not_found() -> crate::errors::Errors {
+    diesel::result::Error::NotFound.into()
+}
+
+#[async_trait]
+impl Repository for InMemoryDb {
+    async fn insert_user(
+        &self,
+        name: &str,
+        phone_number: &str,
+        telegram_id: &Option<UserId>,
+    ) -> Result<()> {
+        let new_user = Users {
+            id: Uuid::new_v4(),
+            telegram_id: telegram_id.unwrap_or(UserId(0)).0 as i32,
+            name: name.to_string(),
+            email: None,
+            phone_number: phone_number.to_string(),
+            height: None,
+            age: None,
+            goal: None,
+            experience: None,
+            days_per_week: None,
+            session_minutes: None,
+        };
+        self.tables.lock().await.users.push(new_user);
+        Ok(())
+    }
+
+    async fn add_email(&self, phone_number: &str, email: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables
+            .users
+            .iter_mut()
+            .filter(|user| user.phone_number == phone_number)
+        {
+            user.email = Some(email.to_string());
+        }
+        Ok(())
+    }
+
+    async fn add_age(&self, phone_number: &str, age: i32) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables
+            .users
+            .iter_mut()
+            .filter(|user| user.phone_number == phone_number)
+        {
+            user.age = Some(age);
+        }
+        Ok(())
+    }
+
+    async fn add_goal(&self, phone_number: &str, goal: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables
+            .users
+            .iter_mut()
+            .filter(|user| user.phone_number == phone_number)
+        {
+            user.goal = Some(goal.to_string());
+        }
+        Ok(())
+    }
+
+    async fn add_experience(&self, phone_number: &str, experience: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables
+            .users
+            .iter_mut()
+            .filter(|user| user.phone_number == phone_number)
+        {
+            user.experience = Some(experience.to_string());
+        }
+        Ok(())
+    }
+
+    async fn add_days_per_week(&self, phone_number: &str, days: i32) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables
+            .users
+            .iter_mut()
+            .filter(|user| user.phone_number == phone_number)
+        {
+            user.days_per_week = Some(days);
+        }
+        Ok(())
+    }
+
+    async fn add_session_minutes(&self, phone_number: &str, minutes: i32) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables
+            .users
+            .iter_mut()
+            .filter(|user| user.phone_number == phone_number)
+        {
+            user.session_minutes = Some(minutes);
+        }
+        Ok(())
+    }
+
+    async fn add_height_and_weight(
+        &self,
+        phone_number: &str,
+        height: i32,
+        weight: i32,
+    ) -> Result<()> {
+        let user_id = {
+            let mut tables = self.tables.lock().await;
+            let user = tables
+                .users
+                .iter_mut()
+                .find(|user| user.phone_number == phone_number)
+                .ok_or_else(not_found)?;
+            user.height = Some(height);
+            user.id
+        };
+        self.add_weight(user_id, weight).await
+    }
+
+    async fn get_user(&self, phone_number: &str) -> Result<Users> {
+        let tables = self.tables.lock().await;
+        tables
+            .users
+            .iter()
+            .find(|user| user.phone_number == phone_number)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    async fn if_user_exists(&self, phone_number: &Option<UserId>) -> Result<bool> {
+        let telegram_id = phone_number.unwrap_or(UserId(0)).0 as i32;
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .users
+            .iter()
+            .any(|user| user.telegram_id == telegram_id))
+    }
+
+    async fn get_user_by_telegram_id(&self, telegram_id: &Option<UserId>) -> Result<Option<Users>> {
+        let telegram_id = telegram_id.unwrap_or(UserId(0)).0 as i32;
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .users
+            .iter()
+            .find(|user| user.telegram_id == telegram_id)
+            .cloned())
+    }
+
+    async fn get_user_by_id(&self, id: Uuid) -> Result<Users> {
+        let tables = self.tables.lock().await;
+        tables
+            .users
+            .iter()
+            .find(|user| user.id == id)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    async fn delete_user(&self, phone_number: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let Some(user_id) = tables
+            .users
+            .iter()
+            .find(|user| user.phone_number == phone_number)
+            .map(|user| user.id)
+        else {
+            return Ok(());
+        };
+        tables.users.retain(|user| user.id != user_id);
+        tables.trainings.retain(|row| row.user_id != user_id);
+        tables.diet_lists.retain(|row| row.user_id != user_id);
+        tables.sizes.retain(|row| row.user_id != user_id);
+        tables.weights.retain(|row| row.user_id != user_id);
+        tables.coach_messages.retain(|row| row.user_id != user_id);
+        tables.token_usage.retain(|row| row.user_id != user_id);
+        tables.generation_jobs.retain(|row| row.user_id != user_id);
+        tables.home_equipment.retain(|row| row.user_id != user_id);
+        Ok(())
+    }
+
+    async fn insert_training(
+        &self,
+        user_id: Uuid,
+        training: Value,
+        status: String,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let new_training = Trainings {
+            id: Uuid::new_v4(),
+            user_id,
+            user_trainings: training,
+            status,
+            created_at: chrono::Utc::now(),
+            updated_at: None,
+            notes,
+        };
+        self.tables.lock().await.trainings.push(new_training);
+        Ok(())
+    }
+
+    async fn get_training(&self, user_id: Uuid, status: String) -> Result<Trainings> {
+        self.get_trainings(user_id, status)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(not_found)
+    }
+
+    async fn get_trainings(&self, user_id: Uuid, status: String) -> Result<Vec<Trainings>> {
+        let tables = self.tables.lock().await;
+        let mut trainings: Vec<Trainings> = tables
+            .trainings
+            .iter()
+            .filter(|training| training.user_id == user_id && training.status == status)
+            .cloned()
+            .collect();
+        trainings.sort_by_key(|training| {
+            std::cmp::Reverse(training.updated_at.unwrap_or(training.created_at))
+        });
+        Ok(trainings)
+    }
+
+    async fn restore_training(&self, user_id: Uuid, id: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for row in tables
+            .trainings
+            .iter_mut()
+            .filter(|row| row.user_id == user_id && row.id == id)
+        {
+            row.updated_at = Some(chrono::Utc::now());
+        }
+        Ok(())
+    }
+
+    async fn update_training(
+        &self,
+        user_id: Uuid,
+        training: Value,
+        status: String,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let id = self.get_training(user_id, status).await?.id;
+        let mut tables = self.tables.lock().await;
+        for row in tables.trainings.iter_mut().filter(|row| row.id == id) {
+            row.user_trainings = training.clone();
+            row.updated_at = Some(chrono::Utc::now());
+            row.notes = notes.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete_training(&self, user_id: Uuid, status: String) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .trainings
+            .retain(|training| !(training.user_id == user_id && training.status == status));
+        Ok(())
+    }
+
+    async fn insert_diet_list(
+        &self,
+        user_id: Uuid,
+        diet_list: Value,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let new_diet_list = DietLists {
+            id: Uuid::new_v4(),
+            user_id,
+            diet_list,
+            created_at: chrono::Utc::now(),
+            updated_at: None,
+            notes,
+        };
+        self.tables.lock().await.diet_lists.push(new_diet_list);
+        Ok(())
+    }
+
+    async fn get_diet_list(&self, user_id: Uuid) -> Result<DietLists> {
+        let tables = self.tables.lock().await;
+        tables
+            .diet_lists
+            .iter()
+            .filter(|diet_list| diet_list.user_id == user_id)
+            .max_by_key(|diet_list| diet_list.created_at)
+            .cloned()
+            .ok_or_else(not_found)
+    }
+
+    async fn get_diet_lists(&self, user_id: Uuid) -> Result<Vec<DietLists>> {
+        let tables = self.tables.lock().await;
+        let mut diet_lists: Vec<DietLists> = tables
+            .diet_lists
+            .iter()
+            .filter(|diet_list| diet_list.user_id == user_id)
+            .cloned()
+            .collect();
+        diet_lists.sort_by_key(|diet_list| diet_list.created_at);
+        Ok(diet_lists)
+    }
+
+    async fn update_diet_list(
+        &self,
+        user_id: Uuid,
+        diet_list: Value,
+        notes: Option<String>,
+    ) -> Result<()> {
+        let id = self.get_diet_list(user_id).await?.id;
+        let mut tables = self.tables.lock().await;
+        for row in tables.diet_lists.iter_mut().filter(|row| row.id == id) {
+            row.diet_list = diet_list.clone();
+            row.updated_at = Some(chrono::Utc::now());
+            row.notes = notes.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete_diet_list(&self, user_id: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .diet_lists
+            .retain(|diet_list| diet_list.user_id != user_id);
+        Ok(())
+    }
+
+    async fn update_age(&self, user_id: Uuid, age: i32) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        for user in tables.users.iter_mut().filter(|user| user.id == user_id) {
+            user.age = Some(age);
+        }
+        Ok(())
+    }
+
+    async fn update_height_and_weight(
+        &self,
+        user_id: Uuid,
+        height: i32,
+        weight: i32,
+    ) -> Result<()> {
+        {
+            let mut tables = self.tables.lock().await;
+            for user in tables.users.iter_mut().filter(|user| user.id == user_id) {
+                user.height = Some(height);
+            }
+        }
+        self.add_weight(user_id, weight).await
+    }
+
+    async fn update_size(
+        &self,
+        user_id: Uuid,
+        size: Vec<String>,
+        measured_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let sizes = Sizes {
+            id: Uuid::new_v4(),
+            user_id,
+            chest: size[0].parse()?,
+            waist: size[1].parse()?,
+            hips: size[2].parse()?,
+            hand_biceps: size[3].parse()?,
+            leg_biceps: size[4].parse()?,
+            calf: size[5].parse()?,
+            measured_at,
+        };
+        self.tables.lock().await.sizes.push(sizes);
+        Ok(())
+    }
+
+    async fn get_size_by_user(&self, user_id: Uuid) -> Result<Option<Sizes>> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .sizes
+            .iter()
+            .filter(|sizes| sizes.user_id == user_id)
+            .max_by_key(|sizes| sizes.measured_at)
+            .cloned())
+    }
+
+    async fn get_sizes_by_user(&self, user_id: Uuid) -> Result<Option<Vec<Sizes>>> {
+        let tables = self.tables.lock().await;
+        let mut sizes: Vec<Sizes> = tables
+            .sizes
+            .iter()
+            .filter(|sizes| sizes.user_id == user_id)
+            .cloned()
+            .collect();
+        sizes.sort_by_key(|sizes| sizes.measured_at);
+        Ok(Some(sizes))
+    }
+
+    async fn add_weight(&self, user_id: Uuid, weight: i32) -> Result<()> {
+        let new_weight = Weights {
+            id: Uuid::new_v4(),
+            user_id,
+            weight,
+            measured_at: chrono::Utc::now(),
+        };
+        self.tables.lock().await.weights.push(new_weight);
+        Ok(())
+    }
+
+    async fn get_latest_weight(&self, user_id: Uuid) -> Result<Option<Weights>> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .weights
+            .iter()
+            .filter(|weight| weight.user_id == user_id)
+            .max_by_key(|weight| weight.measured_at)
+            .cloned())
+    }
+
+    async fn get_weights_by_user(&self, user_id: Uuid) -> Result<Vec<Weights>> {
+        let tables = self.tables.lock().await;
+        let mut weights: Vec<Weights> = tables
+            .weights
+            .iter()
+            .filter(|weight| weight.user_id == user_id)
+            .cloned()
+            .collect();
+        weights.sort_by_key(|weight| weight.measured_at);
+        Ok(weights)
+    }
+
+    async fn add_coach_message(&self, user_id: Uuid, role: &str, content: &str) -> Result<()> {
+        let message = CoachMessages {
+            id: Uuid::new_v4(),
+            user_id,
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at: chrono::Utc::now(),
+        };
+        self.tables.lock().await.coach_messages.push(message);
+        Ok(())
+    }
+
+    async fn get_coach_messages(&self, user_id: Uuid) -> Result<Vec<CoachMessages>> {
+        let tables = self.tables.lock().await;
+        let mut messages: Vec<CoachMessages> = tables
+            .coach_messages
+            .iter()
+            .filter(|message| message.user_id == user_id)
+            .cloned()
+            .collect();
+        messages.sort_by_key(|message| message.created_at);
+        Ok(messages)
+    }
+
+    async fn summarize_coach_messages(
+        &self,
+        user_id: Uuid,
+        until: DateTime<Utc>,
+        summary: &str,
+    ) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .coach_messages
+            .retain(|message| !(message.user_id == user_id && message.created_at <= until));
+        tables.coach_messages.push(CoachMessages {
+            id: Uuid::new_v4(),
+            user_id,
+            role: COACH_SUMMARY_ROLE.to_string(),
+            content: summary.to_string(),
+            created_at: until,
+        });
+        Ok(())
+    }
+
+    async fn delete_coach_messages(&self, user_id: Uuid) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        tables
+            .coach_messages
+            .retain(|message| message.user_id != user_id);
+        Ok(())
+    }
+
+    async fn add_token_usage(
+        &self,
+        user_id: Uuid,
+        feature: &str,
+        prompt_tokens: i32,
+        completion_tokens: i32,
+    ) -> Result<()> {
+        let usage = TokenUsages {
+            id: Uuid::new_v4(),
+            user_id,
+            feature: feature.to_string(),
+            prompt_tokens,
+            completion_tokens,
+            created_at: chrono::Utc::now(),
+        };
+        self.tables.lock().await.token_usage.push(usage);
+        Ok(())
+    }
+
+    async fn get_tokens_used(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<i64> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .token_usage
+            .iter()
+            .filter(|usage| usage.user_id == user_id && usage.created_at >= since)
+            .map(|usage| i64::from(usage.prompt_tokens + usage.completion_tokens))
+            .sum())
+    }
+
+    async fn get_token_usage_since(&self, since: DateTime<Utc>) -> Result<Vec<TokenUsages>> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .token_usage
+            .iter()
+            .filter(|usage| usage.created_at >= since)
+            .cloned()
+            .collect())
+    }
+
+    async fn add_generation_job(&self, job: &GenerationJobs) -> Result<()> {
+        self.tables.lock().await.generation_jobs.push(job.clone());
+        Ok(())
+    }
+
+    async fn get_unfinished_generation_jobs(&self) -> Result<Vec<GenerationJobs>> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .generation_jobs
+            .iter()
+            .filter(|job| job.state == JOB_QUEUED || job.state == JOB_RUNNING)
+            .cloned()
+            .collect())
+    }
+
+    async fn count_unfinished_generation_jobs(&self, user_id: Uuid, feature: &str) -> Result<i64> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .generation_jobs
+            .iter()
+            .filter(|job| job.user_id == user_id && job.feature == feature)
+            .filter(|job| job.state == JOB_QUEUED || job.state == JOB_RUNNING)
+            .count() as i64)
+    }
+
+    async fn get_home_equipment(&self, user_id: Uuid) -> Result<Vec<HomeEquipment>> {
+        let tables = self.tables.lock().await;
+        Ok(tables
+            .home_equipment
+            .iter()
+            .filter(|item| item.user_id == user_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn add_home_equipment(&self, user_id: Uuid, name: &str) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        if tables
+            .home_equipment
+            .iter()
+            .any(|item| item.user_id == user_id && item.name == name)
+        {
+            return Ok(());
+        }
+        tables.home_equipment.push(HomeEquipment {
+            id: Uuid::new_v4(),
+            user_id,
+            name: name.to_string(),
+            created_at: chrono::Utc::now(),
+        });
+        Ok(())
+    }
+
+    async fn delete_home_equipment(&self, user_id: Uuid, name: &str) -> Result<()> {
+        self.tables
+            .lock()
+            .await
+            .home_equipment
+            .retain(|item| item.user_id != user_id || item.name != name);
+        Ok(())
+    }
+
+    async fn clear_home_equipment(&self, user_id: Uuid) -> Result<()> {
+        self.tables
+            .lock()
+            .await
+            .home_equipment
+            .retain(|item| item.user_id != user_id);
+        Ok(())
+    }
+
+    async fn update_generation_job(
+        &self,
+        id: Uuid,
+        state: &str,
+        error: Option<String>,
+    ) -> Result<()> {
+        let mut tables = self.tables.lock().await;
+        let job = tables
+            .generation_jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(not_found)?;
+        job.state = state.to_string();
+        job.error = error;
+        job.updated_at = chrono::Utc::now();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::{COACH_USER_ROLE, FEATURE_TRAINING, GYM_STATE};
+    use chrono::TimeZone;
+
+    const PHONE: &str = "+380000000000";
+
+    async fn user(db: &InMemoryDb) -> Result<Users> {
+        db.insert_user("Test", PHONE, &Some(UserId(1))).await?;
+        db.get_user(PHONE).await
+    }
+
+    fn size(value: i32) -> Vec<String> {
+        vec![value.to_string(); 6]
+    }
+
+    #[tokio::test]
+    async fn delete_user_removes_all_rows() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user = user(&db).await?;
+        let other = Users {
+            id: Uuid::new_v4(),
+            phone_number: "+380111111111".to_string(),
+            ..user.clone()
+        };
+        db.tables.lock().await.users.push(other.clone());
+
+        for user_id in [user.id, other.id] {
+            db.insert_training(user_id, Value::Null, GYM_STATE.to_string(), None)
+                .await?;
+            db.insert_diet_list(user_id, Value::Null, None).await?;
+            db.update_size(user_id, size(50), Utc::now()).await?;
+            db.add_weight(user_id, 80).await?;
+            db.add_coach_message(user_id, COACH_USER_ROLE, "Привіт")
+                .await?;
+            db.add_home_equipment(user_id, "Гантелі").await?;
+        }
+        db.delete_user(PHONE).await?;
+
+        assert!(db.get_user(PHONE).await.is_err());
+        assert!(db
+            .get_trainings(user.id, GYM_STATE.to_string())
+            .await?
+            .is_empty());
+        assert!(db.get_diet_lists(user.id).await?.is_empty());
+        assert!(db.get_size_by_user(user.id).await?.is_none());
+        assert!(db.get_latest_weight(user.id).await?.is_none());
+        assert!(db.get_coach_messages(user.id).await?.is_empty());
+        assert!(db.get_home_equipment(user.id).await?.is_empty());
+
+        assert_eq!(
+            db.get_trainings(other.id, GYM_STATE.to_string())
+                .await?
+                .len(),
+            1
+        );
+        assert_eq!(db.get_home_equipment(other.id).await?.len(), 1);
+        assert!(db.get_latest_weight(other.id).await?.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn sizes_are_ordered_by_measurement_date() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user = user(&db).await?;
+        let day = |day| Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).single();
+        let (Some(first), Some(second), Some(third)) = (day(1), day(10), day(20)) else {
+            panic!("invalid test date");
+        };
+        // An imported older measurement is added after a newer one.
+        db.update_size(user.id, size(92), third).await?;
+        db.update_size(user.id, size(90), first).await?;
+        db.update_size(user.id, size(91), second).await?;
+
+        let sizes = db.get_sizes_by_user(user.id).await?.unwrap_or_default();
+        let chests: Vec<i32> = sizes.iter().map(|size| size.chest).collect();
+        assert_eq!(chests, [90, 91, 92]);
+        let latest = db.get_size_by_user(user.id).await?;
+        assert_eq!(latest.map(|size| size.chest), Some(92));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restored_training_becomes_active() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user = user(&db).await?;
+        db.insert_training(user.id, Value::from("old"), GYM_STATE.to_string(), None)
+            .await?;
+        db.insert_training(user.id, Value::from("new"), GYM_STATE.to_string(), None)
+            .await?;
+        let active = db.get_training(user.id, GYM_STATE.to_string()).await?;
+        assert_eq!(active.user_trainings, Value::from("new"));
+
+        let trainings = db.get_trainings(user.id, GYM_STATE.to_string()).await?;
+        let Some(old) = trainings.last() else {
+            panic!("no trainings");
+        };
+        db.restore_training(user.id, old.id).await?;
+        let active = db.get_training(user.id, GYM_STATE.to_string()).await?;
+        assert_eq!(active.user_trainings, Value::from("old"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn counts_only_unfinished_jobs() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user = user(&db).await?;
+        let job = crate::jobs::new_job(
+            user.id,
+            teloxide::types::ChatId(1),
+            FEATURE_TRAINING,
+            Some(GYM_STATE.to_string()),
+            None,
+        );
+        db.add_generation_job(&job).await?;
+        assert_eq!(
+            db.count_unfinished_generation_jobs(user.id, FEATURE_TRAINING)
+                .await?,
+            1
+        );
+        db.update_generation_job(job.id, crate::consts::JOB_DONE, None)
+            .await?;
+        assert_eq!(
+            db.count_unfinished_generation_jobs(user.id, FEATURE_TRAINING)
+                .await?,
+            0
+        );
+        assert!(db.get_unfinished_generation_jobs().await?.is_empty());
+        Ok(())
+    }
+}
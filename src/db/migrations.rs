@@ -0,0 +1,195 @@
+use crate::db::database::Db;
+use crate::db::schema::{
+    coach_messages, dialogues, diet_lists, generation_jobs, home_equipment, sizes, token_usage,
+    trainings, users, weights,
+};
+use crate::errors::{Errors, Result};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::is_nullable::{IsNullable, NotNull};
+use diesel::sql_types::{Bool, HasSqlType, Oid, SqlType, Text};
+use diesel::PgConnection;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// `text` and `varchar` are interchangeable for diesel, the first migrations use `VARCHAR`.
+const TEXT_OID: u32 = 25;
+const VARCHAR_OID: u32 = 1043;
+
+/// A column as `db::schema` describes it.
+struct ExpectedColumn {
+    table: &'static str,
+    name: &'static str,
+    oid: u32,
+    nullable: bool,
+}
+
+/// A column as the live database describes it.
+#[derive(QueryableByName)]
+struct LiveColumn {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Oid)]
+    oid: u32,
+    #[diesel(sql_type = Bool)]
+    nullable: bool,
+}
+
+trait Nullability {
+    const NULLABLE: bool;
+}
+
+impl Nullability for IsNullable {
+    const NULLABLE: bool = true;
+}
+
+impl Nullability for NotNull {
+    const NULLABLE: bool = false;
+}
+
+fn expected_column<C>(conn: &mut PgConnection, table: &'static str) -> Result<ExpectedColumn>
+where
+    C: Column,
+    C::SqlType: SqlType,
+    <C::SqlType as SqlType>::IsNull: Nullability,
+    Pg: HasSqlType<C::SqlType>,
+{
+    let oid = <Pg as HasSqlType<C::SqlType>>::metadata(conn)
+        .oid()
+        .map_err(|err| Errors::SchemaDriftError(err.to_string()))?;
+    Ok(ExpectedColumn {
+        table,
+        name: C::NAME,
+        oid,
+        nullable: <<C::SqlType as SqlType>::IsNull as Nullability>::NULLABLE,
+    })
+}
+
+/// Lists the expected columns of a table. The columns have to match the
+/// `all_columns` of the table exactly, so a column added to `db::schema`
+/// without being listed here doesn't compile.
+macro_rules! expected_columns {
+    ($conn:expr, $table:ident => $($column:ident),+ $(,)?) => {{
+        let _: <$table::table as Table>::AllColumns = ($($table::$column,)+);
+        vec![$(expected_column::<$table::$column>($conn, stringify!($table))?),+]
+    }};
+}
+
+fn expected_schema(conn: &mut PgConnection) -> Result<Vec<Vec<ExpectedColumn>>> {
+    Ok(vec![
+        expected_columns!(conn, users => id, telegram_id, name, email, phone_number, height, age, goal, experience, days_per_week, session_minutes),
+        expected_columns!(conn, diet_lists => id, user_id, diet_list, created_at, updated_at, notes),
+        expected_columns!(conn, trainings => id, user_id, user_trainings, status, created_at, updated_at, notes),
+        expected_columns!(conn, sizes => id, user_id, chest, waist, hips, hand_biceps, leg_biceps, calf, measured_at),
+        expected_columns!(conn, dialogues => chat_id, state, updated_at),
+        expected_columns!(conn, weights => id, user_id, weight, measured_at),
+        expected_columns!(conn, coach_messages => id, user_id, role, content, created_at),
+        expected_columns!(conn, token_usage => id, user_id, feature, prompt_tokens, completion_tokens, created_at),
+        expected_columns!(conn, home_equipment => id, user_id, name, created_at),
+        expected_columns!(conn, generation_jobs => id, user_id, chat_id, feature, training_status, notes, state, error, created_at, updated_at),
+    ])
+}
+
+fn live_columns(conn: &mut PgConnection, table: &str) -> Result<Vec<LiveColumn>> {
+    let columns = diesel::sql_query(
+        "SELECT a.attname::text AS name, a.atttypid AS oid, NOT a.attnotnull AS nullable \
+         FROM pg_attribute a \
+         JOIN pg_class c ON c.oid = a.attrelid \
+         JOIN pg_namespace n ON n.oid = c.relnamespace \
+         WHERE c.relname = $1 AND n.nspname = current_schema() \
+         AND a.attnum > 0 AND NOT a.attisdropped",
+    )
+    .bind::<Text, _>(table)
+    .load(conn)?;
+    Ok(columns)
+}
+
+fn same_type(expected: u32, live: u32) -> bool {
+    expected == live || (expected == TEXT_OID && live == VARCHAR_OID)
+}
+
+impl Db {
+    /// Applies every embedded migration that is not yet in the database.
+    pub async fn run_migrations(&self) -> Result<()> {
+        self.run(|conn| {
+            let applied = conn
+                .run_pending_migrations(MIGRATIONS)
+                .map_err(|err| Errors::MigrationError(err.to_string()))?;
+            for version in applied {
+                log::info!("Applied migration {}", version);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Compares the live tables with `db::schema` and fails with every mismatch found.
+    pub async fn check_schema(&self) -> Result<()> {
+        self.run(|conn| {
+            let mut mismatches = Vec::new();
+
+            for table in expected_schema(conn)? {
+                let table_name = table[0].table;
+                let live = live_columns(conn, table_name)?;
+                if live.is_empty() {
+                    mismatches.push(format!("table {} is missing", table_name));
+                    continue;
+                }
+
+                for expected in &table {
+                    match live.iter().find(|column| column.name == expected.name) {
+                        Some(column) => {
+                            if !same_type(expected.oid, column.oid) {
+                                mismatches.push(format!(
+                                    "{}.{} has type oid {}, expected {}",
+                                    table_name, expected.name, column.oid, expected.oid
+                                ));
+                            }
+                            if column.nullable != expected.nullable {
+                                mismatches.push(format!(
+                                    "{}.{} is {}, expected {}",
+                                    table_name,
+                                    expected.name,
+                                    nullability(column.nullable),
+                                    nullability(expected.nullable)
+                                ));
+                            }
+                        }
+                        None => {
+                            mismatches.push(format!("{}.{} is missing", table_name, expected.name))
+                        }
+                    }
+                }
+
+                for column in &live {
+                    if !table.iter().any(|expected| expected.name == column.name) {
+                        mismatches.push(format!(
+                            "{}.{} is not in db::schema",
+                            table_name, column.name
+                        ));
+                    }
+                }
+            }
+
+            if mismatches.is_empty() {
+                log::info!("Database schema matches db::schema");
+                Ok(())
+            } else {
+                for mismatch in &mismatches {
+                    log::error!("Schema drift: {}", mismatch);
+                }
+                Err(Errors::SchemaDriftError(mismatches.join("; ")))
+            }
+        })
+        .await
+    }
+}
+
+fn nullability(nullable: bool) -> &'static str {
+    if nullable {
+        "nullable"
+    } else {
+        "not null"
+    }
+}
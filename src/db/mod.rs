@@ -1,3 +1,7 @@
 pub mod database;
+pub mod in_memory;
+pub mod migrations;
 pub mod models;
+pub mod repository;
 pub mod schema;
+pub mod storage;
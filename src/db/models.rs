@@ -1,9 +1,10 @@
 use chrono::{DateTime, Utc};
 use diesel::prelude::*;
+use serde::Serialize;
 use serde_json::Value;
 use uuid::Uuid;
 
-#[derive(Queryable, Selectable, Insertable, Clone)]
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize)]
 #[diesel(table_name = crate::db::schema::users)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Users {
@@ -13,11 +14,16 @@ pub struct Users {
     pub email: Option<String>,
     pub phone_number: String,
     pub height: Option<i32>,
-    pub weight: Option<i32>,
     pub age: Option<i32>,
+    /// Key of a [`crate::models::Goal`].
+    pub goal: Option<String>,
+    /// Key of a [`crate::models::Experience`].
+    pub experience: Option<String>,
+    pub days_per_week: Option<i32>,
+    pub session_minutes: Option<i32>,
 }
 
-#[derive(Queryable, Selectable, Insertable, Debug)]
+#[derive(Queryable, Selectable, Insertable, Clone, Debug, Serialize)]
 #[diesel(table_name = crate::db::schema::diet_lists)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct DietLists {
@@ -26,9 +32,11 @@ pub struct DietLists {
     pub diet_list: Value,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// What the user wrote for the plan, e.g. injuries, kept for later edits.
+    pub notes: Option<String>,
 }
 
-#[derive(Queryable, Selectable, Insertable)]
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize)]
 #[diesel(table_name = crate::db::schema::trainings)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Trainings {
@@ -38,9 +46,11 @@ pub struct Trainings {
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
+    /// What the user wrote for the plan, e.g. injuries, kept for later edits.
+    pub notes: Option<String>,
 }
 
-#[derive(Queryable, Selectable, Insertable, Clone, Default)]
+#[derive(Queryable, Selectable, Insertable, Clone, Default, Serialize)]
 #[diesel(table_name = crate::db::schema::sizes)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct Sizes {
@@ -52,4 +62,78 @@ pub struct Sizes {
     pub hand_biceps: i32,
     pub leg_biceps: i32,
     pub calf: i32,
+    pub measured_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = crate::db::schema::dialogues)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Dialogues {
+    pub chat_id: i64,
+    pub state: Value,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize)]
+#[diesel(table_name = crate::db::schema::weights)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct Weights {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub weight: i32,
+    pub measured_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize)]
+#[diesel(table_name = crate::db::schema::coach_messages)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct CoachMessages {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// "user", "assistant" or "summary" of the older messages.
+    pub role: String,
+    pub content: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = crate::db::schema::token_usage)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct TokenUsages {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// "training", "diet" or "coach".
+    pub feature: String,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone)]
+#[diesel(table_name = crate::db::schema::generation_jobs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct GenerationJobs {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub chat_id: i64,
+    /// "training" or "diet".
+    pub feature: String,
+    /// "Home" or "Gym" for a training.
+    pub training_status: Option<String>,
+    pub notes: Option<String>,
+    /// "queued", "running", "done" or "failed".
+    pub state: String,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Selectable, Insertable, Clone, Serialize)]
+#[diesel(table_name = crate::db::schema::home_equipment)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct HomeEquipment {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
 }
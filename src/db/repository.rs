@@ -0,0 +1,185 @@
+use crate::db::models::{
+    CoachMessages, DietLists, GenerationJobs, HomeEquipment, Sizes, TokenUsages, Trainings, Users,
+    Weights,
+};
+use crate::errors::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use teloxide::prelude::UserId;
+use uuid::Uuid;
+
+/// Storage of users and their plans, measurements, coach chat, token usage,
+/// generation jobs and home equipment used by the handlers.
+///
+/// [`crate::db::database::Db`] keeps everything in Postgres,
+/// [`crate::db::in_memory::InMemoryDb`] keeps everything in memory, so the
+/// dialogue logic can be run without a database.
+#[async_trait]
+pub trait Repository: Send + Sync {
+    async fn insert_user(
+        &self,
+        name: &str,
+        phone_number: &str,
+        telegram_id: &Option<UserId>,
+    ) -> Result<()>;
+
+    async fn add_email(&self, phone_number: &str, email: &str) -> Result<()>;
+
+    async fn add_age(&self, phone_number: &str, age: i32) -> Result<()>;
+
+    async fn add_goal(&self, phone_number: &str, goal: &str) -> Result<()>;
+
+    async fn add_experience(&self, phone_number: &str, experience: &str) -> Result<()>;
+
+    async fn add_days_per_week(&self, phone_number: &str, days: i32) -> Result<()>;
+
+    async fn add_session_minutes(&self, phone_number: &str, minutes: i32) -> Result<()>;
+
+    async fn add_height_and_weight(
+        &self,
+        phone_number: &str,
+        height: i32,
+        weight: i32,
+    ) -> Result<()>;
+
+    async fn get_user(&self, phone_number: &str) -> Result<Users>;
+
+    async fn if_user_exists(&self, phone_number: &Option<UserId>) -> Result<bool>;
+
+    async fn get_user_by_telegram_id(&self, telegram_id: &Option<UserId>) -> Result<Option<Users>>;
+
+    async fn get_user_by_id(&self, id: Uuid) -> Result<Users>;
+
+    /// Removes the user together with every plan, weight and size of the user.
+    async fn delete_user(&self, phone_number: &str) -> Result<()>;
+
+    /// Stores a new active training plan with the `notes` the user wrote for it.
+    async fn insert_training(
+        &self,
+        user_id: Uuid,
+        training: Value,
+        status: String,
+        notes: Option<String>,
+    ) -> Result<()>;
+
+    /// Returns the active training plan of the location.
+    async fn get_training(&self, user_id: Uuid, status: String) -> Result<Trainings>;
+
+    /// Returns every training plan of the location, the active one goes first.
+    async fn get_trainings(&self, user_id: Uuid, status: String) -> Result<Vec<Trainings>>;
+
+    /// Makes a previous training plan the active one.
+    async fn restore_training(&self, user_id: Uuid, id: Uuid) -> Result<()>;
+
+    /// Replaces the content and the notes of the active training plan of the location.
+    async fn update_training(
+        &self,
+        user_id: Uuid,
+        training: Value,
+        status: String,
+        notes: Option<String>,
+    ) -> Result<()>;
+
+    async fn delete_training(&self, user_id: Uuid, status: String) -> Result<()>;
+
+    async fn insert_diet_list(
+        &self,
+        user_id: Uuid,
+        diet_list: Value,
+        notes: Option<String>,
+    ) -> Result<()>;
+
+    /// Returns the newest diet list of the user.
+    async fn get_diet_list(&self, user_id: Uuid) -> Result<DietLists>;
+
+    /// Returns every diet list of the user, the oldest first.
+    async fn get_diet_lists(&self, user_id: Uuid) -> Result<Vec<DietLists>>;
+
+    /// Replaces the newest diet list of the user and its notes.
+    async fn update_diet_list(
+        &self,
+        user_id: Uuid,
+        diet_list: Value,
+        notes: Option<String>,
+    ) -> Result<()>;
+
+    async fn delete_diet_list(&self, user_id: Uuid) -> Result<()>;
+
+    async fn update_age(&self, user_id: Uuid, age: i32) -> Result<()>;
+
+    async fn update_height_and_weight(&self, user_id: Uuid, height: i32, weight: i32)
+        -> Result<()>;
+
+    /// Stores a measurement of chest, waist, hips, arm, thigh and calf taken at `measured_at`.
+    async fn update_size(
+        &self,
+        user_id: Uuid,
+        size: Vec<String>,
+        measured_at: DateTime<Utc>,
+    ) -> Result<()>;
+
+    async fn get_size_by_user(&self, user_id: Uuid) -> Result<Option<Sizes>>;
+
+    async fn get_sizes_by_user(&self, user_id: Uuid) -> Result<Option<Vec<Sizes>>>;
+
+    async fn add_weight(&self, user_id: Uuid, weight: i32) -> Result<()>;
+
+    async fn get_latest_weight(&self, user_id: Uuid) -> Result<Option<Weights>>;
+
+    async fn get_weights_by_user(&self, user_id: Uuid) -> Result<Vec<Weights>>;
+
+    async fn add_coach_message(&self, user_id: Uuid, role: &str, content: &str) -> Result<()>;
+
+    /// Returns the coach conversation of the user, the oldest message first.
+    async fn get_coach_messages(&self, user_id: Uuid) -> Result<Vec<CoachMessages>>;
+
+    /// Replaces the messages up to `until` with a single summary of them.
+    async fn summarize_coach_messages(
+        &self,
+        user_id: Uuid,
+        until: DateTime<Utc>,
+        summary: &str,
+    ) -> Result<()>;
+
+    async fn delete_coach_messages(&self, user_id: Uuid) -> Result<()>;
+
+    async fn add_token_usage(
+        &self,
+        user_id: Uuid,
+        feature: &str,
+        prompt_tokens: i32,
+        completion_tokens: i32,
+    ) -> Result<()>;
+
+    /// Returns how many tokens the user spent since `since`.
+    async fn get_tokens_used(&self, user_id: Uuid, since: DateTime<Utc>) -> Result<i64>;
+
+    /// Returns the token usage of every user since `since`.
+    async fn get_token_usage_since(&self, since: DateTime<Utc>) -> Result<Vec<TokenUsages>>;
+
+    async fn add_generation_job(&self, job: &GenerationJobs) -> Result<()>;
+
+    /// Returns the jobs that are queued or were running, the oldest first.
+    async fn get_unfinished_generation_jobs(&self) -> Result<Vec<GenerationJobs>>;
+
+    /// Returns how many jobs of the feature the user has queued or running.
+    async fn count_unfinished_generation_jobs(&self, user_id: Uuid, feature: &str) -> Result<i64>;
+
+    /// Returns the equipment the user has at home, in the order it was added.
+    async fn get_home_equipment(&self, user_id: Uuid) -> Result<Vec<HomeEquipment>>;
+
+    /// Adds the item unless the user already has one with this name.
+    async fn add_home_equipment(&self, user_id: Uuid, name: &str) -> Result<()>;
+
+    async fn delete_home_equipment(&self, user_id: Uuid, name: &str) -> Result<()>;
+
+    async fn clear_home_equipment(&self, user_id: Uuid) -> Result<()>;
+
+    async fn update_generation_job(
+        &self,
+        id: Uuid,
+        state: &str,
+        error: Option<String>,
+    ) -> Result<()>;
+}
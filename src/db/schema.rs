@@ -6,8 +6,11 @@ diesel::table! {
     email -> Nullable<Text>,
     phone_number -> Text,
     height -> Nullable<Integer>,
-    weight -> Nullable<Integer>,
-    age -> Nullable<Integer>
+    age -> Nullable<Integer>,
+    goal -> Nullable<Text>,
+    experience -> Nullable<Text>,
+    days_per_week -> Nullable<Integer>,
+    session_minutes -> Nullable<Integer>,
     }
 }
 
@@ -17,7 +20,8 @@ diesel::table! {
     user_id -> Uuid,
     diet_list -> Jsonb,
     created_at -> Timestamptz,
-    updated_at -> Nullable<Timestamptz>
+    updated_at -> Nullable<Timestamptz>,
+    notes -> Nullable<Text>,
     }
 }
 
@@ -28,7 +32,8 @@ diesel::table! {
     user_trainings -> Jsonb,
     status -> Text,
     created_at -> Timestamptz,
-    updated_at -> Nullable<Timestamptz>
+    updated_at -> Nullable<Timestamptz>,
+    notes -> Nullable<Text>,
     }
 }
 
@@ -42,5 +47,68 @@ diesel::table! {
     hand_biceps -> Integer,
     leg_biceps -> Integer,
     calf -> Integer,
+    measured_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    dialogues (chat_id) {
+    chat_id -> BigInt,
+    state -> Jsonb,
+    updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    weights (id) {
+    id -> Uuid,
+    user_id -> Uuid,
+    weight -> Integer,
+    measured_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    coach_messages (id) {
+    id -> Uuid,
+    user_id -> Uuid,
+    role -> Text,
+    content -> Text,
+    created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    token_usage (id) {
+    id -> Uuid,
+    user_id -> Uuid,
+    feature -> Text,
+    prompt_tokens -> Integer,
+    completion_tokens -> Integer,
+    created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    generation_jobs (id) {
+    id -> Uuid,
+    user_id -> Uuid,
+    chat_id -> BigInt,
+    feature -> Text,
+    training_status -> Nullable<Text>,
+    notes -> Nullable<Text>,
+    state -> Text,
+    error -> Nullable<Text>,
+    created_at -> Timestamptz,
+    updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    home_equipment (id) {
+    id -> Uuid,
+    user_id -> Uuid,
+    name -> Text,
+    created_at -> Timestamptz,
     }
 }
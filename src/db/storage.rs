@@ -0,0 +1,71 @@
+use crate::db::database::Db;
+use crate::errors::Errors;
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::sync::Arc;
+use teloxide::dispatching::dialogue::Storage;
+use teloxide::types::ChatId;
+
+/// A dialogue storage that keeps every chat state in the `dialogues` table,
+/// so users stay in their menus after the bot restarts.
+pub struct PostgresStorage {
+    db: Arc<Db>,
+}
+
+impl PostgresStorage {
+    pub fn new(db: Arc<Db>) -> Arc<Self> {
+        Arc::new(Self { db })
+    }
+}
+
+impl<D> Storage<D> for PostgresStorage
+where
+    D: Serialize + DeserializeOwned + Send + 'static,
+{
+    type Error = Errors;
+
+    fn remove_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move { self.db.remove_dialogue(chat_id.0).await })
+    }
+
+    fn update_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+        dialogue: D,
+    ) -> BoxFuture<'static, Result<(), Self::Error>>
+    where
+        D: Send + 'static,
+    {
+        Box::pin(async move {
+            let state = serde_json::to_value(dialogue)?;
+            self.db.update_dialogue(chat_id.0, state).await
+        })
+    }
+
+    fn get_dialogue(
+        self: Arc<Self>,
+        chat_id: ChatId,
+    ) -> BoxFuture<'static, Result<Option<D>, Self::Error>> {
+        Box::pin(async move {
+            let state = match self.db.get_dialogue(chat_id.0).await? {
+                Some(state) => state,
+                None => return Ok(None),
+            };
+            // A state saved by an older version of the bot starts the dialogue from scratch
+            match serde_json::from_value(state) {
+                Ok(dialogue) => Ok(Some(dialogue)),
+                Err(err) => {
+                    log::warn!("Cannot restore dialogue for chat {}: {}", chat_id, err);
+                    Ok(None)
+                }
+            }
+        })
+    }
+}
@@ -19,11 +19,14 @@ pub enum Errors {
     #[error(transparent)]
     TeloxideError(#[from] teloxide::RequestError),
 
+    #[error(transparent)]
+    DownloadError(#[from] teloxide::DownloadError),
+
     #[error(transparent)]
     ParseLevelError(#[from] log::ParseLevelError),
 
     #[error(transparent)]
-    InMemStorageError(#[from] teloxide::dispatching::dialogue::InMemStorageError),
+    StorageError(#[from] Box<dyn std::error::Error + Send + Sync>),
 
     #[error(transparent)]
     SetLoggerError(#[from] log::SetLoggerError),
@@ -37,11 +40,89 @@ pub enum Errors {
     #[error(transparent)]
     ParseIntError(#[from] std::num::ParseIntError),
 
+    #[error(transparent)]
+    ParseFloatError(#[from] std::num::ParseFloatError),
+
     #[error(transparent)]
     BitMapBackendError(#[from] DrawingAreaErrorKind<BitMapBackendError>),
 
+    #[error(transparent)]
+    CsvError(#[from] csv::Error),
+
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+
+    #[error(transparent)]
+    PoolError(#[from] diesel::r2d2::PoolError),
+
+    #[error(transparent)]
+    JoinError(#[from] tokio::task::JoinError),
+
+    #[error("Language model rate limit exceeded")]
+    RateLimited,
+
+    #[error("Language model answer was blocked by the content filter")]
+    ContentFiltered,
+
+    #[error("Language model returned an empty answer")]
+    EmptyResponse,
+
+    #[error("Language model did not answer in time")]
+    LlmTimeout,
+
+    #[error("Daily token quota exceeded")]
+    DailyQuotaExceeded,
+
+    #[error("Monthly token quota exceeded")]
+    MonthlyQuotaExceeded,
+
+    #[error("Generation job queue is closed")]
+    JobQueueClosed,
+
+    #[error("User text looks like a prompt injection")]
+    PromptInjection,
+
+    #[error("Language model answer is not a valid plan: {0}")]
+    InvalidPlan(String),
+
+    #[error("Invalid prompt template {0}")]
+    TemplateError(String),
+
+    #[error("Cannot run migrations: {0}")]
+    MigrationError(String),
+
+    #[error("Database schema does not match db::schema: {0}")]
+    SchemaDriftError(String),
+}
+
+impl Errors {
+    /// Text shown to the user when generating an answer with the language model fails.
+    pub fn user_message(&self) -> &'static str {
+        match self {
+            Errors::RateLimited => {
+                "Зараз забагато запитів до штучного інтелекту, спробуй ще раз за хвилину"
+            }
+            Errors::ContentFiltered => {
+                "Штучний інтелект відмовився відповідати на цей запит, спробуй сформулювати його інакше"
+            }
+            Errors::EmptyResponse => "Штучний інтелект повернув порожню відповідь, спробуй ще раз",
+            Errors::LlmTimeout => "Штучний інтелект не встиг відповісти, спробуй ще раз пізніше",
+            Errors::InvalidPlan(_) => {
+                "Штучний інтелект склав план з помилками, спробуй ще раз"
+            }
+            Errors::DailyQuotaExceeded => {
+                "Ти вичерпав денний ліміт запитів до штучного інтелекту, спробуй завтра"
+            }
+            Errors::MonthlyQuotaExceeded => {
+                "Ти вичерпав місячний ліміт запитів до штучного інтелекту, він оновиться першого числа"
+            }
+            Errors::PromptInjection => {
+                "Схоже, що повідомлення намагається змінити інструкції бота, тому я його не виконую. \
+                 Напиши свої побажання чи питання звичайними словами"
+            }
+            _ => "Не вдалося згенерувати відповідь, спробуй ще раз пізніше",
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Errors>;
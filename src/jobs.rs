@@ -0,0 +1,212 @@
+use crate::api_calls::diet::process_diet;
+use crate::api_calls::live::LiveMessage;
+use crate::api_calls::trainings::process_training;
+use crate::async_openai::metered::MeteredLlm;
+use crate::async_openai::provider::LlmProviders;
+use crate::consts::{
+    FEATURE_DIET, FEATURE_TRAINING, GYM_STATE, JOB_DONE, JOB_FAILED, JOB_QUEUED, JOB_RUNNING,
+};
+use crate::db::models::GenerationJobs;
+use crate::db::repository::Repository;
+use crate::errors::{Errors, Result};
+use crate::plans::preview;
+use crate::prompts::Prompts;
+use crate::quotas::Quotas;
+use std::sync::Arc;
+use teloxide::prelude::*;
+use teloxide::Bot;
+use tokio::sync::{mpsc, watch, Semaphore};
+use uuid::Uuid;
+
+/// Plans generated at the same time when `GENERATION_WORKERS` is not set.
+const DEFAULT_WORKERS: usize = 2;
+
+/// Generates plans in the background, so the user can use the menus while the
+/// model writes. Every job is saved before it is queued and the ones a restart
+/// interrupted are run again.
+pub struct Jobs {
+    db: Arc<dyn Repository>,
+    sender: mpsc::UnboundedSender<GenerationJobs>,
+}
+
+/// What a worker needs to generate a plan and send it to the chat.
+struct Worker {
+    bot: Bot,
+    db: Arc<dyn Repository>,
+    llms: Arc<LlmProviders>,
+    quotas: Arc<Quotas>,
+    prompts: Arc<Prompts>,
+}
+
+impl Jobs {
+    /// Starts the workers, at most `GENERATION_WORKERS` plans are generated at once,
+    /// and queues the jobs left unfinished by the previous run.
+    pub async fn start(
+        bot: Bot,
+        db: Arc<dyn Repository>,
+        llms: Arc<LlmProviders>,
+        quotas: Arc<Quotas>,
+        prompts: Arc<Prompts>,
+    ) -> Result<Self> {
+        let workers = dotenv::var("GENERATION_WORKERS")
+            .ok()
+            .map(|workers| workers.trim().parse::<usize>())
+            .transpose()?
+            .unwrap_or(DEFAULT_WORKERS)
+            .max(1);
+        let unfinished = db.get_unfinished_generation_jobs().await?;
+        log::info!(
+            "Starting {} generation workers, resuming {} jobs",
+            workers,
+            unfinished.len()
+        );
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let worker = Worker {
+            bot,
+            db: Arc::clone(&db),
+            llms,
+            quotas,
+            prompts,
+        };
+        tokio::spawn(dispatch(Arc::new(worker), receiver, workers));
+        for job in unfinished {
+            sender.send(job).map_err(|_| Errors::JobQueueClosed)?;
+        }
+        Ok(Self { db, sender })
+    }
+
+    /// Saves the job and queues it, the plan is sent to the chat when it is ready.
+    pub async fn submit(&self, job: GenerationJobs) -> Result<()> {
+        self.db.add_generation_job(&job).await?;
+        log::info!("Queued {} generation job {}", job.feature, job.id);
+        self.sender.send(job).map_err(|_| Errors::JobQueueClosed)
+    }
+}
+
+/// A queued job for a plan of `feature`, `training_status` is "Home" or "Gym" for trainings.
+pub fn new_job(
+    user_id: Uuid,
+    chat_id: ChatId,
+    feature: &str,
+    training_status: Option<String>,
+    notes: Option<String>,
+) -> GenerationJobs {
+    let now = chrono::Utc::now();
+    GenerationJobs {
+        id: Uuid::new_v4(),
+        user_id,
+        chat_id: chat_id.0,
+        feature: feature.to_string(),
+        training_status,
+        notes,
+        state: JOB_QUEUED.to_string(),
+        error: None,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// Takes the jobs in the order they were queued, each one waits for a free worker.
+async fn dispatch(
+    worker: Arc<Worker>,
+    mut receiver: mpsc::UnboundedReceiver<GenerationJobs>,
+    workers: usize,
+) {
+    let slots = Arc::new(Semaphore::new(workers));
+    while let Some(job) = receiver.recv().await {
+        let Ok(slot) = Arc::clone(&slots).acquire_owned().await else {
+            break;
+        };
+        let worker = Arc::clone(&worker);
+        tokio::spawn(async move {
+            worker.run(job).await;
+            drop(slot);
+        });
+    }
+}
+
+impl Worker {
+    async fn run(&self, job: GenerationJobs) {
+        let (state, error) = match self.generate(&job).await {
+            Ok(()) => (JOB_DONE, None),
+            Err(err) => {
+                log::error!("Generation job {} failed: {}", job.id, err);
+                (JOB_FAILED, Some(err.to_string()))
+            }
+        };
+        if let Err(err) = self.db.update_generation_job(job.id, state, error).await {
+            log::error!("Cannot finish generation job {}: {}", job.id, err);
+        }
+    }
+
+    /// Generates the plan, showing its preview in a placeholder, and sends it as
+    /// a new message. The user gets the reason when it fails.
+    async fn generate(&self, job: &GenerationJobs) -> Result<()> {
+        self.db
+            .update_generation_job(job.id, JOB_RUNNING, None)
+            .await?;
+        let user = self.db.get_user_by_id(job.user_id).await?;
+        let db = self.db.as_ref();
+        let diet = job.feature == FEATURE_DIET;
+        let title = if diet {
+            "Складаю дієту..."
+        } else {
+            "Складаю тренування..."
+        };
+
+        let live = LiveMessage::start(&self.bot, ChatId(job.chat_id), title).await?;
+        let (progress, answer) = watch::channel(String::new());
+        let result: Result<String> = live
+            .track(answer, preview, async {
+                self.quotas.check(db, user.id).await?;
+                if diet {
+                    let llm =
+                        MeteredLlm::new(self.llms.get(FEATURE_DIET), db, user.id, FEATURE_DIET);
+                    let plan = process_diet(
+                        job.notes.as_deref(),
+                        &llm,
+                        &self.prompts,
+                        user,
+                        db,
+                        &progress,
+                    )
+                    .await?;
+                    Ok(format!("Дієта додана! \n\n А ось і вона: \n\n {}", plan))
+                } else {
+                    let llm = MeteredLlm::new(
+                        self.llms.get(FEATURE_TRAINING),
+                        db,
+                        user.id,
+                        FEATURE_TRAINING,
+                    );
+                    let status = job
+                        .training_status
+                        .clone()
+                        .unwrap_or_else(|| GYM_STATE.to_string());
+                    let plan = process_training(
+                        job.notes.as_deref(),
+                        &llm,
+                        &self.prompts,
+                        user,
+                        db,
+                        status,
+                        &progress,
+                    )
+                    .await?;
+                    Ok(format!(
+                        "Тренування додано! \n\n А ось і воно: \n\n {}",
+                        plan
+                    ))
+                }
+            })
+            .await;
+
+        let text = match &result {
+            Ok(text) => text.clone(),
+            Err(err) => err.user_message().to_string(),
+        };
+        live.deliver(text).await?;
+        result.map(drop)
+    }
+}
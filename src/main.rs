@@ -1,16 +1,20 @@
-use crate::async_openai::client::OpenAiClient;
-use crate::db::database::Db;
 use crate::errors::Result;
+use crate::jobs::Jobs;
 use crate::models::State;
-use crate::utils::{init_logging, schema};
+use crate::prompts::Prompts;
+use crate::quotas::Quotas;
+use crate::utils::{init_logging, make_llm_providers, make_storages, schema};
 use dotenv::dotenv;
 use std::sync::Arc;
-use teloxide::dispatching::dialogue::InMemStorage;
 use teloxide::prelude::*;
-use tokio::sync::Mutex;
 
 mod db;
+mod jobs;
 mod models;
+mod plans;
+mod prompts;
+mod quotas;
+mod safety;
 mod utils;
 
 mod api_calls;
@@ -26,20 +30,31 @@ async fn main() -> Result<()> {
 
     let bot = Bot::new(dotenv::var("TELOXIDE_TOKEN")?);
 
-    let database_url = dotenv::var("DATABASE_URL")?;
-
-    let open_ai_token = dotenv::var("OPENAI_API_KEY")?;
-
-    let open_ai_client = Arc::new(Mutex::new(OpenAiClient::new(open_ai_token)));
-    let db = Arc::new(Mutex::new(Db::new(&database_url)));
+    let llms = make_llm_providers()?;
+    let (db, storage) = make_storages().await?;
+    let quotas = Arc::new(Quotas::from_env()?);
+    let prompts = Arc::new(Prompts::from_env()?);
     let state = Arc::new(State::Start);
+    let jobs = Arc::new(
+        Jobs::start(
+            bot.clone(),
+            Arc::clone(&db),
+            Arc::clone(&llms),
+            Arc::clone(&quotas),
+            Arc::clone(&prompts),
+        )
+        .await?,
+    );
 
     Dispatcher::builder(bot, schema())
         .dependencies(dptree::deps![
-            InMemStorage::<State>::new(),
+            storage,
             Arc::clone(&db),
             Arc::clone(&state),
-            Arc::clone(&open_ai_client)
+            Arc::clone(&llms),
+            Arc::clone(&quotas),
+            Arc::clone(&prompts),
+            Arc::clone(&jobs)
         ])
         .enable_ctrlc_handler()
         .build()
@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
-use teloxide::dispatching::dialogue::InMemStorage;
+use teloxide::dispatching::dialogue::ErasedStorage;
 use teloxide::prelude::Dialogue;
 use teloxide::utils::command::BotCommands;
+use uuid::Uuid;
 
-pub type MyDialogue = Dialogue<State, InMemStorage<State>>;
+pub type MyDialogue = Dialogue<State, ErasedStorage<State>>;
 
 /// These commands are supported:
 #[derive(BotCommands, Clone)]
@@ -18,6 +20,12 @@ pub enum Command {
     Start,
     #[command(description = "cancel the purchase procedure.")]
     Cancel,
+    #[command(description = "export all of your data.")]
+    Export,
+    #[command(description = "off")]
+    Usage,
+    #[command(description = "off")]
+    ReloadPrompts,
 }
 
 pub enum MenuCommands {
@@ -25,10 +33,11 @@ pub enum MenuCommands {
     MyGymTrainings,
     MyDiet,
     Data,
+    Coach,
     GoBack,
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub enum State {
     #[default]
     Start,
@@ -42,16 +51,45 @@ pub enum State {
     GetWeightAndHeight {
         phone_number: String,
     },
+    /// The fitness profile questions, asked after registration and "Оновити дані".
+    GetGoal {
+        phone_number: String,
+        registration: bool,
+    },
+    GetExperience {
+        phone_number: String,
+        registration: bool,
+    },
+    GetDaysPerWeek {
+        phone_number: String,
+        registration: bool,
+    },
+    GetSessionLength {
+        phone_number: String,
+        registration: bool,
+    },
     ChangeMenu {
         phone_number: String,
     },
     HomeTrainingMenu {
         phone_number: String,
     },
+    HomeEquipment {
+        phone_number: String,
+    },
     AddTraining {
         phone_number: String,
         training_state: String,
     },
+    TrainingHistory {
+        phone_number: String,
+        training_state: String,
+    },
+    TrainingVersion {
+        phone_number: String,
+        training_state: String,
+        training_id: Uuid,
+    },
     GymTrainingMenu {
         phone_number: String,
     },
@@ -70,6 +108,19 @@ pub enum State {
     UpdateSize {
         phone_number: String,
     },
+    DeleteAccount {
+        phone_number: String,
+    },
+    CoachChat {
+        phone_number: String,
+    },
+    EditTraining {
+        phone_number: String,
+        training_state: String,
+    },
+    EditDiet {
+        phone_number: String,
+    },
 }
 
 impl Display for MenuCommands {
@@ -79,6 +130,7 @@ impl Display for MenuCommands {
             MenuCommands::MyGymTrainings => write!(f, "Мої тренування"),
             MenuCommands::MyDiet => write!(f, "Моє харчування"),
             MenuCommands::Data => write!(f, "Мої дані"),
+            MenuCommands::Coach => write!(f, "Тренер ШІ"),
             MenuCommands::GoBack => write!(f, "На головну"),
         }
     }
@@ -91,6 +143,7 @@ impl From<String> for MenuCommands {
             "Мої тренування" => MenuCommands::MyGymTrainings,
             "Моє харчування" => MenuCommands::MyDiet,
             "Мої дані" => MenuCommands::Data,
+            "Тренер ШІ" => MenuCommands::Coach,
             "На головну" => MenuCommands::GoBack,
             _ => MenuCommands::GoBack,
         }
@@ -99,8 +152,11 @@ impl From<String> for MenuCommands {
 
 pub enum TrainingsCommands {
     AddTraining,
+    EditTraining,
     DeleteTraining,
     ShowTrainings,
+    History,
+    Equipment,
     GoBack,
 }
 
@@ -108,8 +164,11 @@ impl Display for TrainingsCommands {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TrainingsCommands::AddTraining => write!(f, "Додати тренування"),
+            TrainingsCommands::EditTraining => write!(f, "Змінити тренування"),
             TrainingsCommands::DeleteTraining => write!(f, "Видалити тренування"),
             TrainingsCommands::ShowTrainings => write!(f, "Показати тренування"),
+            TrainingsCommands::History => write!(f, "Історія тренувань"),
+            TrainingsCommands::Equipment => write!(f, "Мій інвентар"),
             TrainingsCommands::GoBack => write!(f, "На головну"),
         }
     }
@@ -119,8 +178,11 @@ impl From<String> for TrainingsCommands {
     fn from(s: String) -> Self {
         match s.as_str() {
             "Додати тренування" => TrainingsCommands::AddTraining,
+            "Змінити тренування" => TrainingsCommands::EditTraining,
             "Видалити тренування" => TrainingsCommands::DeleteTraining,
             "Показати тренування" => TrainingsCommands::ShowTrainings,
+            "Історія тренувань" => TrainingsCommands::History,
+            "Мій інвентар" => TrainingsCommands::Equipment,
             "На головну" => TrainingsCommands::GoBack,
 
             _ => TrainingsCommands::GoBack,
@@ -128,8 +190,84 @@ impl From<String> for TrainingsCommands {
     }
 }
 
+/// Buttons of the home equipment inventory, any other text is an item name.
+pub enum EquipmentCommands {
+    Clear,
+    Done,
+    Item(String),
+}
+
+impl Display for EquipmentCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EquipmentCommands::Clear => write!(f, "Очистити інвентар"),
+            EquipmentCommands::Done => write!(f, "Готово"),
+            EquipmentCommands::Item(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<String> for EquipmentCommands {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Очистити інвентар" => EquipmentCommands::Clear,
+            "Готово" => EquipmentCommands::Done,
+            _ => EquipmentCommands::Item(s),
+        }
+    }
+}
+
+pub enum TrainingHistoryCommands {
+    Restore,
+    GoBack,
+}
+
+impl Display for TrainingHistoryCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrainingHistoryCommands::Restore => write!(f, "Відновити"),
+            TrainingHistoryCommands::GoBack => write!(f, "Назад"),
+        }
+    }
+}
+
+impl From<String> for TrainingHistoryCommands {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Відновити" => TrainingHistoryCommands::Restore,
+            "Назад" => TrainingHistoryCommands::GoBack,
+            _ => TrainingHistoryCommands::GoBack,
+        }
+    }
+}
+
+pub enum CoachCommands {
+    NewChat,
+    GoBack,
+}
+
+impl Display for CoachCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoachCommands::NewChat => write!(f, "Нова розмова"),
+            CoachCommands::GoBack => write!(f, "На головну"),
+        }
+    }
+}
+
+impl From<String> for CoachCommands {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Нова розмова" => CoachCommands::NewChat,
+            "На головну" => CoachCommands::GoBack,
+            _ => CoachCommands::GoBack,
+        }
+    }
+}
+
 pub enum DietCommands {
     AddDiet,
+    EditDiet,
     DeleteDiet,
     ShowDiet,
     GoBack,
@@ -139,6 +277,7 @@ impl Display for DietCommands {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             DietCommands::AddDiet => write!(f, "Додати дієту"),
+            DietCommands::EditDiet => write!(f, "Змінити дієту"),
             DietCommands::DeleteDiet => write!(f, "Видалити дієту"),
             DietCommands::ShowDiet => write!(f, "Показати дієту"),
             DietCommands::GoBack => write!(f, "На головну"),
@@ -150,6 +289,7 @@ impl From<String> for DietCommands {
     fn from(s: String) -> Self {
         match s.as_str() {
             "Додати дієту" => DietCommands::AddDiet,
+            "Змінити дієту" => DietCommands::EditDiet,
             "Видалити дієту" => DietCommands::DeleteDiet,
             "Показати дієту" => DietCommands::ShowDiet,
             "На головну" => DietCommands::GoBack,
@@ -163,6 +303,7 @@ pub enum DataCommands {
     UpdateSize,
     ShowData,
     ShowStatistics,
+    DeleteAccount,
     GoBack,
 }
 
@@ -173,6 +314,7 @@ impl Display for DataCommands {
             DataCommands::UpdateSize => write!(f, "Оновити розміри тіла"),
             DataCommands::ShowData => write!(f, "Показати дані"),
             DataCommands::ShowStatistics => write!(f, "Показати статистику"),
+            DataCommands::DeleteAccount => write!(f, "Видалити акаунт"),
             DataCommands::GoBack => write!(f, "На головну"),
         }
     }
@@ -185,8 +327,151 @@ impl From<String> for DataCommands {
             "Оновити розміри тіла" => DataCommands::UpdateSize,
             "Показати дані" => DataCommands::ShowData,
             "Показати статистику" => DataCommands::ShowStatistics,
+            "Видалити акаунт" => DataCommands::DeleteAccount,
             "На головну" => DataCommands::GoBack,
             _ => DataCommands::GoBack,
         }
     }
 }
+
+pub enum DeleteAccountCommands {
+    Confirm,
+    Cancel,
+}
+
+impl Display for DeleteAccountCommands {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeleteAccountCommands::Confirm => write!(f, "Так, видалити все"),
+            DeleteAccountCommands::Cancel => write!(f, "Ні, залишити"),
+        }
+    }
+}
+
+impl From<String> for DeleteAccountCommands {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "Так, видалити все" => DeleteAccountCommands::Confirm,
+            "Ні, залишити" => DeleteAccountCommands::Cancel,
+            _ => DeleteAccountCommands::Cancel,
+        }
+    }
+}
+
+/// The main thing the user trains for.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Goal {
+    FatLoss,
+    MuscleGain,
+    Strength,
+    Endurance,
+    Health,
+}
+
+impl Goal {
+    pub const ALL: [Goal; 5] = [
+        Goal::FatLoss,
+        Goal::MuscleGain,
+        Goal::Strength,
+        Goal::Endurance,
+        Goal::Health,
+    ];
+
+    /// Value stored in the database.
+    pub fn key(self) -> &'static str {
+        match self {
+            Goal::FatLoss => "fat_loss",
+            Goal::MuscleGain => "muscle_gain",
+            Goal::Strength => "strength",
+            Goal::Endurance => "endurance",
+            Goal::Health => "health",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|goal| goal.key() == key)
+    }
+
+    pub fn from_button(text: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|goal| goal.to_string() == text)
+    }
+
+    /// How the goal is put in the prompts.
+    pub fn description(self) -> &'static str {
+        match self {
+            Goal::FatLoss => "схуднути та зменшити відсоток жиру",
+            Goal::MuscleGain => "набрати м'язову масу",
+            Goal::Strength => "стати сильнішим",
+            Goal::Endurance => "покращити витривалість",
+            Goal::Health => "підтримувати загальне здоров'я та форму",
+        }
+    }
+}
+
+impl Display for Goal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Goal::FatLoss => write!(f, "Схуднути"),
+            Goal::MuscleGain => write!(f, "Набрати м'язи"),
+            Goal::Strength => write!(f, "Стати сильнішим"),
+            Goal::Endurance => write!(f, "Витривалість"),
+            Goal::Health => write!(f, "Загальне здоров'я"),
+        }
+    }
+}
+
+/// How long the user has been training.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Experience {
+    Beginner,
+    Intermediate,
+    Advanced,
+}
+
+impl Experience {
+    pub const ALL: [Experience; 3] = [
+        Experience::Beginner,
+        Experience::Intermediate,
+        Experience::Advanced,
+    ];
+
+    /// Value stored in the database.
+    pub fn key(self) -> &'static str {
+        match self {
+            Experience::Beginner => "beginner",
+            Experience::Intermediate => "intermediate",
+            Experience::Advanced => "advanced",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|experience| experience.key() == key)
+    }
+
+    pub fn from_button(text: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|experience| experience.to_string() == text)
+    }
+
+    /// How the experience is put in the prompts.
+    pub fn description(self) -> &'static str {
+        match self {
+            Experience::Beginner => "новачок, тренуюсь менше пів року",
+            Experience::Intermediate => "тренуюсь від пів року до двох років",
+            Experience::Advanced => "тренуюсь понад два роки",
+        }
+    }
+}
+
+impl Display for Experience {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Experience::Beginner => write!(f, "Новачок (до пів року)"),
+            Experience::Intermediate => write!(f, "Середній (пів року - 2 роки)"),
+            Experience::Advanced => write!(f, "Досвідчений (понад 2 роки)"),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use crate::errors::Result;
+use crate::plans::Plan;
+use crate::safety::plans::{diet_hazards, warning, Limits};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+use std::iter::Sum;
+use std::ops::Add;
+
+/// Limits that no sane diet plan crosses.
+const MAX_DAYS: usize = 7;
+const MAX_PORTION_GRAMS: u32 = 2000;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DietPlan {
+    pub days: Vec<DietDay>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DietDay {
+    pub day: String,
+    pub meals: Vec<Meal>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Meal {
+    pub name: String,
+    pub foods: Vec<Food>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Food {
+    pub name: String,
+    pub grams: u32,
+    #[serde(flatten)]
+    pub nutrients: Nutrients,
+}
+
+/// Energy and macros of a portion, grams of protein, fat and carbs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Nutrients {
+    pub kcal: f64,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbs: f64,
+}
+
+impl Add for Nutrients {
+    type Output = Nutrients;
+
+    fn add(self, other: Nutrients) -> Nutrients {
+        Nutrients {
+            kcal: self.kcal + other.kcal,
+            protein: self.protein + other.protein,
+            fat: self.fat + other.fat,
+            carbs: self.carbs + other.carbs,
+        }
+    }
+}
+
+impl Sum for Nutrients {
+    fn sum<I: Iterator<Item = Nutrients>>(iter: I) -> Nutrients {
+        iter.fold(Nutrients::default(), Add::add)
+    }
+}
+
+impl Display for Nutrients {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.0} ккал, Б {:.0} / Ж {:.0} / В {:.0}",
+            self.kcal, self.protein, self.fat, self.carbs
+        )
+    }
+}
+
+impl Meal {
+    pub fn totals(&self) -> Nutrients {
+        self.foods.iter().map(|food| food.nutrients).sum()
+    }
+}
+
+impl DietDay {
+    pub fn totals(&self) -> Nutrients {
+        self.meals.iter().map(Meal::totals).sum()
+    }
+}
+
+impl Plan for DietPlan {
+    const FORMAT: &'static str = "Відповідай тільки JSON об'єктом без пояснень у такому форматі: \
+{\"days\": [{\"day\": \"Понеділок\", \"meals\": [{\"name\": \"Сніданок\", \
+\"foods\": [{\"name\": \"Вівсянка на воді\", \"grams\": 80, \"kcal\": 290, \"protein\": 10, \
+\"fat\": 5, \"carbs\": 50}]}]}], \"notes\": \"Порада\"}. \
+kcal, protein, fat і carbs вказуй для всієї порції. \
+В notes нагадай що краще це узгодити з тренером або дієтологом, якщо є.";
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.days.is_empty() {
+            return Err("немає жодного дня".to_string());
+        }
+        if self.days.len() > MAX_DAYS {
+            return Err(format!("більше {} днів", MAX_DAYS));
+        }
+        for day in &self.days {
+            if day.day.trim().is_empty() {
+                return Err("у дня немає назви".to_string());
+            }
+            if day.meals.is_empty() {
+                return Err(format!("{}: немає прийомів їжі", day.day));
+            }
+            for meal in &day.meals {
+                if meal.foods.is_empty() {
+                    return Err(format!("{}: {} без продуктів", day.day, meal.name));
+                }
+                for food in &meal.foods {
+                    if food.name.trim().is_empty() {
+                        return Err(format!("{}: у продукту немає назви", day.day));
+                    }
+                    if food.grams == 0 || food.grams > MAX_PORTION_GRAMS {
+                        return Err(format!(
+                            "{}: {} має {} г, дозволено 1-{}",
+                            day.day, food.name, food.grams, MAX_PORTION_GRAMS
+                        ));
+                    }
+                    let Nutrients {
+                        kcal,
+                        protein,
+                        fat,
+                        carbs,
+                    } = food.nutrients;
+                    if [kcal, protein, fat, carbs]
+                        .iter()
+                        .any(|value| !value.is_finite() || *value < 0.0)
+                    {
+                        return Err(format!(
+                            "{}: у {} від'ємні калорії або макроси",
+                            day.day, food.name
+                        ));
+                    }
+                }
+            }
+            if day.totals().kcal <= 0.0 {
+                return Err(format!("{}: нуль калорій за день", day.day));
+            }
+        }
+        Ok(())
+    }
+
+    fn hazards(&self, limits: &Limits) -> Vec<String> {
+        diet_hazards(self, limits)
+    }
+
+    fn annotate(&mut self, hazards: &[String]) {
+        self.notes = Some(warning(hazards, self.notes.as_deref()));
+    }
+}
+
+impl Display for DietPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for day in &self.days {
+            writeln!(f, "📅 {}", day.day)?;
+            for meal in &day.meals {
+                writeln!(f, "🍽 {} ({:.0} ккал)", meal.name, meal.totals().kcal)?;
+                for food in &meal.foods {
+                    writeln!(f, "• {} {} г — {}", food.name, food.grams, food.nutrients)?;
+                }
+            }
+            writeln!(f, "Разом за день: {}", day.totals())?;
+            writeln!(f)?;
+        }
+        if let Some(notes) = &self.notes {
+            write!(f, "{}", notes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text of a stored diet. Diets made before the plans were typed are plain strings.
+pub fn diet_text(diet_list: &Value) -> Result<String> {
+    match diet_list {
+        Value::String(text) => Ok(text.clone()),
+        plan => Ok(serde_json::from_value::<DietPlan>(plan.clone())?.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plans::validation_problem;
+
+    fn food(name: &str, grams: u32, kcal: f64, protein: f64, fat: f64, carbs: f64) -> Food {
+        Food {
+            name: name.to_string(),
+            grams,
+            nutrients: Nutrients {
+                kcal,
+                protein,
+                fat,
+                carbs,
+            },
+        }
+    }
+
+    fn plan() -> DietPlan {
+        DietPlan {
+            days: vec![DietDay {
+                day: "Понеділок".to_string(),
+                meals: vec![
+                    Meal {
+                        name: "Сніданок".to_string(),
+                        foods: vec![
+                            food("Вівсянка", 80, 290.0, 10.0, 5.0, 50.0),
+                            food("Яйця", 120, 186.0, 15.0, 13.0, 1.0),
+                        ],
+                    },
+                    Meal {
+                        name: "Обід".to_string(),
+                        foods: vec![food("Гречка", 200, 220.0, 8.0, 2.0, 42.0)],
+                    },
+                ],
+            }],
+            notes: Some("Узгодь з дієтологом".to_string()),
+        }
+    }
+
+    #[test]
+    fn sums_meal_and_day_totals() {
+        let plan = plan();
+        let day = &plan.days[0];
+        assert_eq!(
+            day.meals[0].totals(),
+            Nutrients {
+                kcal: 476.0,
+                protein: 25.0,
+                fat: 18.0,
+                carbs: 51.0
+            }
+        );
+        assert_eq!(
+            day.totals(),
+            Nutrients {
+                kcal: 696.0,
+                protein: 33.0,
+                fat: 20.0,
+                carbs: 93.0
+            }
+        );
+        assert_eq!(
+            Vec::<Nutrients>::new().into_iter().sum::<Nutrients>(),
+            Nutrients::default()
+        );
+    }
+
+    #[test]
+    fn accepts_plan_within_bounds() {
+        assert_eq!(plan().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_plan_out_of_bounds() {
+        let problem = |change| validation_problem(plan(), change);
+        assert!(problem(|plan| plan.days.clear()).contains("немає жодного дня"));
+        assert!(
+            problem(|plan| plan.days = vec![plan.days[0].clone(); MAX_DAYS + 1])
+                .contains("більше 7 днів")
+        );
+        assert!(problem(|plan| plan.days[0].meals.clear()).contains("немає прийомів їжі"));
+        assert!(problem(|plan| plan.days[0].meals[1].foods.clear()).contains("Обід без продуктів"));
+        assert!(problem(|plan| plan.days[0].meals[0].foods[0].grams = 0).contains("дозволено"));
+        assert!(
+            problem(|plan| plan.days[0].meals[0].foods[0].grams = MAX_PORTION_GRAMS + 1)
+                .contains("дозволено")
+        );
+        assert!(
+            problem(|plan| plan.days[0].meals[0].foods[0].nutrients.fat = -1.0)
+                .contains("від'ємні")
+        );
+        assert!(
+            problem(|plan| plan.days[0].meals[0].foods[0].nutrients.kcal = f64::NAN)
+                .contains("від'ємні")
+        );
+        assert!(problem(|plan| {
+            for meal in &mut plan.days[0].meals {
+                for food in &mut meal.foods {
+                    food.nutrients.kcal = 0.0;
+                }
+            }
+        })
+        .contains("нуль калорій"));
+    }
+
+    #[test]
+    fn shows_stored_diets() -> Result<()> {
+        let legacy = Value::from("Сніданок: вівсянка");
+        assert_eq!(diet_text(&legacy)?, "Сніданок: вівсянка");
+
+        let text = diet_text(&serde_json::to_value(plan())?)?;
+        assert!(text.contains("🍽 Сніданок (476 ккал)"));
+        assert!(text.contains("• Гречка 200 г — 220 ккал, Б 8 / Ж 2 / В 42"));
+        assert!(text.contains("Разом за день: 696 ккал, Б 33 / Ж 20 / В 93"));
+        assert!(text.ends_with("Узгодь з дієтологом"));
+        Ok(())
+    }
+
+    #[test]
+    fn reads_flattened_nutrients() -> Result<()> {
+        let banana: Food = serde_json::from_str(
+            r#"{"name": "Банан", "grams": 120, "kcal": 107, "protein": 1.3, "fat": 0.4, "carbs": 27}"#,
+        )?;
+        assert_eq!(banana, food("Банан", 120, 107.0, 1.3, 0.4, 27.0));
+        Ok(())
+    }
+}
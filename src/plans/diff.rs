@@ -0,0 +1,130 @@
+/// Every day of a rendered plan starts with it.
+const DAY_MARK: &str = "📅";
+
+/// Lines of the rendered plans that were removed or added, each change is preceded
+/// by the day it belongs to when that day itself didn't change.
+pub fn diff(before: &str, after: &str) -> Vec<String> {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+
+    // common[i][j] is the longest common subsequence of before[i..] and after[j..].
+    let mut common = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+    for i in (0..before.len()).rev() {
+        for j in (0..after.len()).rev() {
+            common[i][j] = if before[i] == after[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let mut day = None;
+    let mut change = |sign: &str, line: &str, day: &mut Option<&str>| {
+        // A changed day is a day of its own, not a change of the previous one.
+        if line.starts_with(DAY_MARK) {
+            *day = None;
+        } else if let Some(day) = day.take() {
+            changes.push(day.to_string());
+        }
+        changes.push(format!("{} {}", sign, line));
+    };
+
+    let (mut i, mut j) = (0, 0);
+    while i < before.len() || j < after.len() {
+        if i < before.len() && j < after.len() && before[i] == after[j] {
+            if before[i].starts_with(DAY_MARK) {
+                day = Some(before[i]);
+            }
+            i += 1;
+            j += 1;
+        } else if i < before.len() && (j == after.len() || common[i + 1][j] >= common[i][j + 1]) {
+            if !before[i].trim().is_empty() {
+                change("➖", before[i], &mut day);
+            }
+            i += 1;
+        } else {
+            if !after[j].trim().is_empty() {
+                change("➕", after[j], &mut day);
+            }
+            j += 1;
+        }
+    }
+    changes
+}
+
+/// The diff as a message, or a note that nothing changed.
+pub fn changes_text(before: &str, after: &str) -> String {
+    let changes = diff(before, after);
+    if changes.is_empty() {
+        "Змін немає".to_string()
+    } else {
+        format!("Зміни: \n\n{}", changes.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN: &str = "📅 Понеділок (60 хв)\n• Присідання: 3×12\n• Жим лежачи: 4×8\n\n📅 Середа (45 хв)\n• Тяга: 3×10";
+
+    #[test]
+    fn identical_plans_have_no_changes() {
+        assert!(diff(PLAN, PLAN).is_empty());
+        assert_eq!(changes_text(PLAN, PLAN), "Змін немає");
+    }
+
+    #[test]
+    fn changed_line_is_removed_and_added_under_its_day() {
+        let after = PLAN.replace("Жим лежачи: 4×8", "Жим лежачи: 3×10");
+        assert_eq!(
+            diff(PLAN, &after),
+            [
+                "📅 Понеділок (60 хв)",
+                "➖ • Жим лежачи: 4×8",
+                "➕ • Жим лежачи: 3×10"
+            ]
+        );
+        assert_eq!(
+            changes_text(PLAN, &after),
+            "Зміни: \n\n📅 Понеділок (60 хв)\n➖ • Жим лежачи: 4×8\n➕ • Жим лежачи: 3×10"
+        );
+    }
+
+    #[test]
+    fn added_and_removed_lines() {
+        let after = PLAN
+            .replace("• Присідання: 3×12\n", "")
+            .replace("• Тяга: 3×10", "• Тяга: 3×10\n• Планка: 3×60 с");
+        assert_eq!(
+            diff(PLAN, &after),
+            [
+                "📅 Понеділок (60 хв)",
+                "➖ • Присідання: 3×12",
+                "📅 Середа (45 хв)",
+                "➕ • Планка: 3×60 с"
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_day_is_not_repeated() {
+        let after = PLAN.replace("📅 Середа (45 хв)", "📅 Четвер (45 хв)");
+        assert_eq!(
+            diff(PLAN, &after),
+            ["➖ 📅 Середа (45 хв)", "➕ 📅 Четвер (45 хв)"]
+        );
+    }
+
+    #[test]
+    fn empty_plans() {
+        assert!(diff("", "").is_empty());
+        assert_eq!(
+            diff("", "📅 Понеділок\n\n• Тяга"),
+            ["➕ 📅 Понеділок", "➕ • Тяга"]
+        );
+        assert_eq!(diff("• Тяга", ""), ["➖ • Тяга"]);
+    }
+}
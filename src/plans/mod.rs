@@ -0,0 +1,278 @@
+use crate::async_openai::provider::{ChatMessage, ChatRole, LlmProvider, Progress};
+use crate::consts::PROMPT_USER_TEXT;
+use crate::errors::{Errors, Result};
+use crate::prompts::{PromptKind, Prompts, Variables};
+use crate::safety::injection::screen;
+use crate::safety::plans::Limits;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+pub mod diet;
+pub mod diff;
+pub mod training;
+
+/// How many times a broken answer is sent back to the model to be fixed.
+const MAX_REPAIRS: usize = 2;
+
+/// A plan the model answers with as JSON.
+pub trait Plan: DeserializeOwned {
+    /// Instructions appended to the prompt describing the expected JSON.
+    const FORMAT: &'static str;
+
+    /// Checks what serde can't, returns the reason the plan is not usable.
+    fn validate(&self) -> std::result::Result<(), String>;
+
+    /// What makes the plan unsafe for the user, empty when nothing does.
+    fn hazards(&self, limits: &Limits) -> Vec<String>;
+
+    /// Warns about the hazards the model didn't fix.
+    fn annotate(&mut self, hazards: &[String]);
+}
+
+/// Asks the model for a plan, an answer that doesn't parse or validate is sent
+/// back together with the problem until it is fixed or the repairs run out.
+/// A plan that breaks the safety `limits` is sent back the same way and gets
+/// a warning when the repairs run out. The text the user wrote goes in its own
+/// message after the instructions and is screened first. Every answer is
+/// streamed to `progress`.
+pub async fn request_plan<T: Plan>(
+    llm: &dyn LlmProvider,
+    instructions: &str,
+    user_text: Option<&str>,
+    limits: &Limits,
+    progress: &Progress,
+) -> Result<T> {
+    let mut messages = match user_text {
+        Some(text) => {
+            screen(text)?;
+            vec![
+                ChatMessage::new(
+                    ChatRole::System,
+                    format!("{}\n\n{}\n\n{}", instructions, T::FORMAT, PROMPT_USER_TEXT),
+                ),
+                ChatMessage::new(ChatRole::User, text),
+            ]
+        }
+        None => vec![ChatMessage::new(
+            ChatRole::System,
+            format!("{}\n\n{}", instructions, T::FORMAT),
+        )],
+    };
+    let mut answer = llm.chat_stream(&messages, progress).await?.content;
+    let mut repairs = 0;
+    loop {
+        let repair = match parse_plan::<T>(&answer) {
+            Ok(mut plan) => {
+                let hazards = plan.hazards(limits);
+                if hazards.is_empty() {
+                    return Ok(plan);
+                }
+                if repairs == MAX_REPAIRS {
+                    log::warn!("Plan is still unsafe, saving it with a warning");
+                    plan.annotate(&hazards);
+                    return Ok(plan);
+                }
+                format!(
+                    "Цей план небезпечний для клієнта: {}\n\n\
+                     Виправ це і поверни тільки JSON.\n\n{}",
+                    hazards.join("; "),
+                    T::FORMAT
+                )
+            }
+            Err(problem) => {
+                if repairs == MAX_REPAIRS {
+                    return Err(Errors::InvalidPlan(problem));
+                }
+                format!(
+                    "Остання відповідь не відповідає формату: {}\n\n\
+                     Виправ її і поверни тільки JSON.\n\n{}",
+                    problem,
+                    T::FORMAT
+                )
+            }
+        };
+        repairs += 1;
+        log::warn!("Asking for plan repair {}: {}", repairs, repair);
+        messages.push(ChatMessage::new(ChatRole::Assistant, answer));
+        messages.push(ChatMessage::new(ChatRole::System, repair));
+        answer = llm.chat_stream(&messages, progress).await?.content;
+    }
+}
+
+/// Asks the model to change the stored plan as the user requested.
+pub async fn edit_plan<T: Plan>(
+    llm: &dyn LlmProvider,
+    prompts: &Prompts,
+    plan: &Value,
+    request: &str,
+    limits: &Limits,
+    progress: &Progress,
+) -> Result<T> {
+    // Plans made before they were typed are stored as plain text.
+    let plan = match plan {
+        Value::String(text) => text.clone(),
+        plan => plan.to_string(),
+    };
+    let instructions = prompts.render(PromptKind::EditPlan, &Variables::from([("plan", plan)]));
+    request_plan(llm, &instructions, Some(request), limits, progress).await
+}
+
+/// Parses the JSON object of the answer, ignoring any text or markdown fences around it.
+fn parse_plan<T: Plan>(answer: &str) -> std::result::Result<T, String> {
+    let start = answer.find('{').ok_or("у відповіді немає JSON об'єкта")?;
+    let end = answer.rfind('}').ok_or("JSON об'єкт не закритий")?;
+    if end < start {
+        return Err("JSON об'єкт не закритий".to_string());
+    }
+    let plan: T = serde_json::from_str(&answer[start..=end]).map_err(|err| err.to_string())?;
+    plan.validate()?;
+    Ok(plan)
+}
+
+/// Lists the days and the names of exercises, meals and foods of a plan that
+/// is still being written. The JSON doesn't parse until it is complete, so
+/// only the finished strings are read, nested names are indented.
+pub fn preview(answer: &str) -> String {
+    let mut lines = Vec::new();
+    let mut depth = 0usize;
+    let mut key: Option<String> = None;
+    let mut chars = answer.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            ',' | '{' | '}' => key = None,
+            '"' => {
+                let Some(text) = read_string(&mut chars) else {
+                    break;
+                };
+                while chars.next_if(|char| char.is_whitespace()).is_some() {}
+                if chars.next_if_eq(&':').is_some() {
+                    key = Some(text);
+                    continue;
+                }
+                match key.take().as_deref() {
+                    Some("day") => lines.push(format!("📅 {}", text)),
+                    Some("name") => lines.push(format!(
+                        "{}• {}",
+                        "   ".repeat(depth.saturating_sub(2)),
+                        text
+                    )),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    lines.join("\n")
+}
+
+/// Reads a JSON string after its opening quote, `None` if it is not finished yet.
+fn read_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => match chars.next()? {
+                'n' | 't' | 'r' => text.push(' '),
+                'u' => {
+                    let code: String = (0..4).map(|_| chars.next()).collect::<Option<_>>()?;
+                    text.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+                }
+                char => text.push(char),
+            },
+            char => text.push(char),
+        }
+    }
+}
+
+/// What `validate` says about `plan` after `change`, empty when it still passes.
+#[cfg(test)]
+pub(crate) fn validation_problem<T: Plan>(mut plan: T, change: fn(&mut T)) -> String {
+    change(&mut plan);
+    plan.validate().err().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_openai::mock::MockProvider;
+    use crate::plans::training::TrainingPlan;
+
+    const PLAN: &str = r#"{"days": [{"day": "Понеділок", "duration_minutes": 45, "exercises": [
+        {"name": "Присідання", "sets": 3, "reps": "12", "rest_seconds": 60}]}]}"#;
+
+    /// The plan with zero sets, it parses but doesn't validate.
+    const INVALID_PLAN: &str = r#"{"days": [{"day": "Понеділок", "duration_minutes": 45, "exercises": [
+        {"name": "Присідання", "sets": 0, "reps": "12", "rest_seconds": 60}]}]}"#;
+
+    async fn request(llm: &MockProvider) -> Result<TrainingPlan> {
+        request_with(llm, None).await
+    }
+
+    async fn request_with(llm: &MockProvider, notes: Option<&str>) -> Result<TrainingPlan> {
+        let (progress, _answer) = tokio::sync::watch::channel(String::new());
+        let limits = Limits::new(None, None, notes);
+        request_plan(llm, "Склади план", notes, &limits, &progress).await
+    }
+
+    #[test]
+    fn ignores_text_and_fences_around_json() {
+        let answer = format!("Ось твій план:\n```json\n{}\n```\nУспіхів!", PLAN);
+        assert!(parse_plan::<TrainingPlan>(&answer).is_ok());
+    }
+
+    #[test]
+    fn rejects_answers_without_valid_json() {
+        assert!(parse_plan::<TrainingPlan>("Вибач, не можу").is_err());
+        assert!(parse_plan::<TrainingPlan>("} не той порядок {").is_err());
+        assert!(parse_plan::<TrainingPlan>(r#"{"days": "понеділок"}"#).is_err());
+        assert!(parse_plan::<TrainingPlan>(INVALID_PLAN)
+            .is_err_and(|problem| problem.contains("підходів")));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repairs_broken_answer() -> Result<()> {
+        let llm = MockProvider::scripted(["Ось план: понеділок присідання", PLAN]);
+        let plan = request(&llm).await?;
+        assert_eq!(plan.days[0].exercises[0].name, "Присідання");
+
+        let requests = llm.requests();
+        assert_eq!(requests.len(), 2);
+        let repair = &requests[1];
+        assert_eq!(repair[1].role, ChatRole::Assistant);
+        assert_eq!(repair[1].content, "Ось план: понеділок присідання");
+        assert_eq!(repair[2].role, ChatRole::System);
+        assert!(repair[2].content.contains("не відповідає формату"));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn repairs_plan_that_does_not_validate() -> Result<()> {
+        let llm = MockProvider::scripted([INVALID_PLAN, PLAN]);
+        request(&llm).await?;
+        let requests = llm.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1][2].content.contains("0 підходів"));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_max_repairs() {
+        let llm = MockProvider::scripted(["не JSON"; MAX_REPAIRS + 2]);
+        let result = request(&llm).await;
+        assert!(matches!(result, Err(Errors::InvalidPlan(_))));
+        assert_eq!(llm.requests().len(), MAX_REPAIRS + 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn warns_when_plan_stays_unsafe() -> Result<()> {
+        let llm = MockProvider::scripted([PLAN; MAX_REPAIRS + 1]);
+        let plan = request_with(&llm, Some("болить коліно")).await?;
+        assert_eq!(llm.requests().len(), MAX_REPAIRS + 1);
+        assert!(plan
+            .notes
+            .is_some_and(|notes| notes.contains("Присідання не можна при травмі коліна")));
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::errors::Result;
+use crate::plans::Plan;
+use crate::safety::plans::{training_hazards, warning, Limits};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Display;
+
+/// Limits that no sane training plan crosses.
+const MAX_DAYS: usize = 7;
+const MAX_SETS: u32 = 10;
+const MAX_DURATION_MINUTES: u32 = 240;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrainingPlan {
+    pub days: Vec<TrainingDay>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrainingDay {
+    pub day: String,
+    #[serde(default)]
+    pub focus: Option<String>,
+    pub duration_minutes: u32,
+    pub exercises: Vec<Exercise>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Exercise {
+    pub name: String,
+    pub sets: u32,
+    /// Either a count or a range like "8-12", or a time like "30 с".
+    pub reps: String,
+    pub rest_seconds: u32,
+    #[serde(default)]
+    pub equipment: Option<String>,
+}
+
+impl Plan for TrainingPlan {
+    const FORMAT: &'static str = "Відповідай тільки JSON об'єктом без пояснень у такому форматі: \
+{\"days\": [{\"day\": \"Понеділок\", \"focus\": \"Груди та трицепс\", \"duration_minutes\": 60, \
+\"exercises\": [{\"name\": \"Жим лежачи\", \"sets\": 4, \"reps\": \"8-10\", \"rest_seconds\": 90, \
+\"equipment\": \"Штанга\"}]}], \"notes\": \"Порада\"}. \
+Дні без тренувань не додавай. В notes нагадай що краще це узгодити з тренером, якщо є.";
+
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.days.is_empty() {
+            return Err("немає жодного дня тренувань".to_string());
+        }
+        if self.days.len() > MAX_DAYS {
+            return Err(format!("більше {} днів тренувань", MAX_DAYS));
+        }
+        for day in &self.days {
+            if day.day.trim().is_empty() {
+                return Err("у дня немає назви".to_string());
+            }
+            if day.exercises.is_empty() {
+                return Err(format!("{}: немає вправ", day.day));
+            }
+            if day.duration_minutes == 0 || day.duration_minutes > MAX_DURATION_MINUTES {
+                return Err(format!(
+                    "{}: тривалість {} хв поза межами 1-{}",
+                    day.day, day.duration_minutes, MAX_DURATION_MINUTES
+                ));
+            }
+            for exercise in &day.exercises {
+                if exercise.name.trim().is_empty() {
+                    return Err(format!("{}: у вправи немає назви", day.day));
+                }
+                if exercise.sets == 0 || exercise.sets > MAX_SETS {
+                    return Err(format!(
+                        "{}: {} має {} підходів, дозволено 1-{}",
+                        day.day, exercise.name, exercise.sets, MAX_SETS
+                    ));
+                }
+                if exercise.reps.trim().is_empty() {
+                    return Err(format!("{}: {} без повторень", day.day, exercise.name));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn hazards(&self, limits: &Limits) -> Vec<String> {
+        training_hazards(self, limits)
+    }
+
+    fn annotate(&mut self, hazards: &[String]) {
+        self.notes = Some(warning(hazards, self.notes.as_deref()));
+    }
+}
+
+impl Display for TrainingPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for day in &self.days {
+            write!(f, "📅 {}", day.day)?;
+            if let Some(focus) = &day.focus {
+                write!(f, " — {}", focus)?;
+            }
+            writeln!(f, " ({} хв)", day.duration_minutes)?;
+            for exercise in &day.exercises {
+                write!(
+                    f,
+                    "• {}: {}×{}, відпочинок {} с",
+                    exercise.name, exercise.sets, exercise.reps, exercise.rest_seconds
+                )?;
+                if let Some(equipment) = &exercise.equipment {
+                    write!(f, ", {}", equipment)?;
+                }
+                writeln!(f)?;
+            }
+            writeln!(f)?;
+        }
+        if let Some(notes) = &self.notes {
+            write!(f, "{}", notes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text of a stored training. Trainings made before the plans were typed are plain strings.
+pub fn training_text(user_trainings: &Value) -> Result<String> {
+    match user_trainings {
+        Value::String(text) => Ok(text.clone()),
+        plan => Ok(serde_json::from_value::<TrainingPlan>(plan.clone())?.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plans::validation_problem;
+
+    fn plan() -> TrainingPlan {
+        TrainingPlan {
+            days: vec![TrainingDay {
+                day: "Понеділок".to_string(),
+                focus: None,
+                duration_minutes: 60,
+                exercises: vec![Exercise {
+                    name: "Присідання".to_string(),
+                    sets: 3,
+                    reps: "12".to_string(),
+                    rest_seconds: 60,
+                    equipment: None,
+                }],
+            }],
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn accepts_plan_within_bounds() {
+        assert_eq!(plan().validate(), Ok(()));
+        let mut plan = plan();
+        plan.days = vec![plan.days[0].clone(); MAX_DAYS];
+        plan.days[0].duration_minutes = MAX_DURATION_MINUTES;
+        plan.days[0].exercises[0].sets = MAX_SETS;
+        assert_eq!(plan.validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_plan_out_of_bounds() {
+        let problem = |change| validation_problem(plan(), change);
+        assert!(problem(|plan| plan.days.clear()).contains("немає жодного дня"));
+        assert!(
+            problem(|plan| plan.days = vec![plan.days[0].clone(); MAX_DAYS + 1])
+                .contains("більше 7 днів")
+        );
+        assert!(problem(|plan| plan.days[0].day = " ".to_string()).contains("немає назви"));
+        assert!(problem(|plan| plan.days[0].exercises.clear()).contains("немає вправ"));
+        assert!(problem(|plan| plan.days[0].duration_minutes = 0).contains("тривалість"));
+        assert!(
+            problem(|plan| plan.days[0].duration_minutes = MAX_DURATION_MINUTES + 1)
+                .contains("тривалість")
+        );
+        assert!(problem(|plan| plan.days[0].exercises[0].name.clear()).contains("немає назви"));
+        assert!(problem(|plan| plan.days[0].exercises[0].sets = 0).contains("підходів"));
+        assert!(problem(|plan| plan.days[0].exercises[0].sets = MAX_SETS + 1).contains("підходів"));
+        assert!(problem(|plan| plan.days[0].exercises[0].reps.clear()).contains("без повторень"));
+    }
+
+    #[test]
+    fn shows_stored_plans() -> Result<()> {
+        let legacy = Value::from("Понеділок: присідання 3x12");
+        assert_eq!(training_text(&legacy)?, "Понеділок: присідання 3x12");
+
+        let text = training_text(&serde_json::to_value(plan())?)?;
+        assert!(text.starts_with("📅 Понеділок (60 хв)"));
+        assert!(text.contains("• Присідання: 3×12, відпочинок 60 с"));
+        Ok(())
+    }
+}
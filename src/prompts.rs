@@ -0,0 +1,370 @@
+use crate::consts::{
+    PROMPT_COACH, PROMPT_COACH_SUMMARY, PROMPT_DIET, PROMPT_EDIT_PLAN, PROMPT_GYM_TRAINING,
+    PROMPT_HOME_TRAINING,
+};
+use crate::db::models::Users;
+use crate::db::repository::Repository;
+use crate::errors::{Errors, Result};
+use crate::models::{Experience, Goal};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{PoisonError, RwLock};
+
+/// Minutes of a training when the user didn't say how long it can be.
+const DEFAULT_SESSION_MINUTES: i32 = 90;
+
+/// Values of the placeholders, the ones the bot doesn't know are left out.
+pub type Variables = HashMap<&'static str, String>;
+
+/// Every prompt the bot sends, each one can be replaced by a file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PromptKind {
+    HomeTraining,
+    GymTraining,
+    Diet,
+    Coach,
+    CoachSummary,
+    EditPlan,
+}
+
+impl PromptKind {
+    const ALL: [PromptKind; 6] = [
+        PromptKind::HomeTraining,
+        PromptKind::GymTraining,
+        PromptKind::Diet,
+        PromptKind::Coach,
+        PromptKind::CoachSummary,
+        PromptKind::EditPlan,
+    ];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            PromptKind::HomeTraining => "home_training.txt",
+            PromptKind::GymTraining => "gym_training.txt",
+            PromptKind::Diet => "diet.txt",
+            PromptKind::Coach => "coach.txt",
+            PromptKind::CoachSummary => "coach_summary.txt",
+            PromptKind::EditPlan => "edit_plan.txt",
+        }
+    }
+
+    fn default_template(self) -> &'static str {
+        match self {
+            PromptKind::HomeTraining => PROMPT_HOME_TRAINING,
+            PromptKind::GymTraining => PROMPT_GYM_TRAINING,
+            PromptKind::Diet => PROMPT_DIET,
+            PromptKind::Coach => PROMPT_COACH,
+            PromptKind::CoachSummary => PROMPT_COACH_SUMMARY,
+            PromptKind::EditPlan => PROMPT_EDIT_PLAN,
+        }
+    }
+
+    /// Placeholders the template may use.
+    fn variables(self) -> &'static [&'static str] {
+        match self {
+            PromptKind::HomeTraining => &[
+                "age",
+                "height",
+                "weight",
+                "sizes",
+                "goal",
+                "experience",
+                "days_per_week",
+                "session_minutes",
+                "equipment",
+            ],
+            PromptKind::GymTraining | PromptKind::Diet => &[
+                "age",
+                "height",
+                "weight",
+                "sizes",
+                "goal",
+                "experience",
+                "days_per_week",
+                "session_minutes",
+            ],
+            PromptKind::Coach => &[
+                "name",
+                "age",
+                "height",
+                "weight",
+                "sizes",
+                "goal",
+                "experience",
+                "days_per_week",
+                "session_minutes",
+                "gym_training",
+                "home_training",
+                "diet",
+            ],
+            PromptKind::CoachSummary => &[],
+            PromptKind::EditPlan => &["plan"],
+        }
+    }
+}
+
+/// A prompt with `{name}` placeholders. A line with a placeholder the bot has
+/// no value for is left out, so every optional fact goes on its own line.
+struct Template {
+    text: String,
+}
+
+impl Template {
+    fn parse(kind: PromptKind, text: String) -> Result<Self> {
+        let mut rest = text.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').ok_or_else(|| {
+                Errors::TemplateError(format!("{}: '{{' is not closed", kind.file_name()))
+            })?;
+            let name = &rest[start + 1..start + end];
+            if !kind.variables().contains(&name) {
+                return Err(Errors::TemplateError(format!(
+                    "{}: unknown placeholder {{{}}}, expected one of {}",
+                    kind.file_name(),
+                    name,
+                    kind.variables().join(", ")
+                )));
+            }
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self { text })
+    }
+
+    fn render(&self, variables: &Variables) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        'lines: for line in self.text.lines() {
+            let mut rendered = String::new();
+            let mut rest = line;
+            while let Some(start) = rest.find('{') {
+                let Some(end) = rest[start..].find('}') else {
+                    break;
+                };
+                match variables.get(&rest[start + 1..start + end]) {
+                    Some(value) if !value.trim().is_empty() => {
+                        rendered.push_str(&rest[..start]);
+                        rendered.push_str(value.trim());
+                    }
+                    _ => continue 'lines,
+                }
+                rest = &rest[start + end + 1..];
+            }
+            rendered.push_str(rest);
+            // Left out lines must not leave gaps between paragraphs.
+            if rendered.trim().is_empty() && lines.last().is_none_or(String::is_empty) {
+                continue;
+            }
+            lines.push(rendered.trim_end().to_string());
+        }
+        lines.join("\n").trim().to_string()
+    }
+}
+
+/// The prompt templates, read from `PROMPTS_DIR` when it is set. A template
+/// without a file there keeps its default from `consts`.
+pub struct Prompts {
+    dir: Option<PathBuf>,
+    templates: RwLock<HashMap<PromptKind, Template>>,
+}
+
+impl Prompts {
+    pub fn from_env() -> Result<Self> {
+        let dir = dotenv::var("PROMPTS_DIR").ok().map(PathBuf::from);
+        let templates = load_templates(dir.as_ref())?;
+        Ok(Self {
+            dir,
+            templates: RwLock::new(templates),
+        })
+    }
+
+    /// Reads the files again, the old templates stay if any of them is invalid.
+    pub fn reload(&self) -> Result<()> {
+        let templates = load_templates(self.dir.as_ref())?;
+        *self
+            .templates
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = templates;
+        Ok(())
+    }
+
+    pub fn render(&self, kind: PromptKind, variables: &Variables) -> String {
+        let templates = self
+            .templates
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Every kind is loaded, with its default at least.
+        templates[&kind].render(variables)
+    }
+}
+
+fn load_templates(dir: Option<&PathBuf>) -> Result<HashMap<PromptKind, Template>> {
+    let mut templates = HashMap::new();
+    for kind in PromptKind::ALL {
+        let path = dir.map(|dir| dir.join(kind.file_name()));
+        let text = match path {
+            Some(path) if path.exists() => {
+                log::info!("Loading prompt template {}", path.display());
+                std::fs::read_to_string(path)?
+            }
+            _ => kind.default_template().to_string(),
+        };
+        templates.insert(kind, Template::parse(kind, text)?);
+    }
+    Ok(templates)
+}
+
+/// What the bot knows about the user: age, height, latest weight and sizes,
+/// and the goal, experience and time for training.
+pub async fn profile_variables(db: &dyn Repository, user: &Users) -> Result<Variables> {
+    let mut variables = Variables::new();
+    variables.insert("name", user.name.clone());
+    if let Some(age) = user.age {
+        variables.insert("age", age.to_string());
+    }
+    if let Some(height) = user.height {
+        variables.insert("height", height.to_string());
+    }
+    if let Some(goal) = user.goal.as_deref().and_then(Goal::from_key) {
+        variables.insert("goal", goal.description().to_string());
+    }
+    if let Some(experience) = user.experience.as_deref().and_then(Experience::from_key) {
+        variables.insert("experience", experience.description().to_string());
+    }
+    if let Some(days) = user.days_per_week {
+        variables.insert("days_per_week", days.to_string());
+    }
+    // Without an answer the session length keeps the limit the bot always had.
+    let minutes = user.session_minutes.unwrap_or(DEFAULT_SESSION_MINUTES);
+    variables.insert("session_minutes", minutes.to_string());
+    if let Some(weight) = db.get_latest_weight(user.id).await? {
+        variables.insert("weight", weight.weight.to_string());
+    }
+    let sizes = db.get_sizes_by_user(user.id).await?.unwrap_or_default();
+    if let Some(size) = sizes.last() {
+        variables.insert(
+            "sizes",
+            format!(
+                "груди {} см, талія {} см, стегна {} см, біцепс {} см, стегно {} см, литка {} см",
+                size.chest, size.waist, size.hips, size.hand_biceps, size.leg_biceps, size.calf
+            ),
+        );
+    }
+    Ok(variables)
+}
+
+/// Notes of an edited plan: the ones it was made with and the edit request, so a
+/// condition mentioned once keeps limiting every later version of the plan.
+pub fn edited_notes(notes: Option<&str>, request: &str) -> String {
+    match notes {
+        Some(notes) => format!("{}\n{}", notes, request),
+        None => request.to_string(),
+    }
+}
+
+/// The wishes the user wrote for a plan. The bot asks to send a dot when
+/// there are none, so text of punctuation alone means no notes.
+pub fn user_notes(text: Option<&str>) -> Option<String> {
+    let text = text?.trim();
+    if text
+        .chars()
+        .all(|char| char.is_ascii_punctuation() || char.is_whitespace())
+    {
+        return None;
+    }
+    Some(text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::in_memory::InMemoryDb;
+    use teloxide::types::UserId;
+
+    fn template(kind: PromptKind, text: &str) -> Result<Template> {
+        Template::parse(kind, text.to_string())
+    }
+
+    #[test]
+    fn default_templates_parse() -> Result<()> {
+        assert_eq!(load_templates(None)?.len(), PromptKind::ALL.len());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_unknown_and_unclosed_placeholders() {
+        assert!(matches!(
+            template(PromptKind::Diet, "Мій зріст: {heigth} см."),
+            Err(Errors::TemplateError(message)) if message.contains("unknown placeholder {heigth}")
+        ));
+        assert!(matches!(
+            template(PromptKind::Diet, "Мій зріст: {height см."),
+            Err(Errors::TemplateError(message)) if message.contains("is not closed")
+        ));
+        assert!(template(PromptKind::EditPlan, "Мій вік: {age}.").is_err());
+        assert!(template(PromptKind::Diet, "Мій вік: {age}.").is_ok());
+    }
+
+    #[test]
+    fn leaves_out_lines_without_values() -> Result<()> {
+        let template = template(
+            PromptKind::Diet,
+            "Склади дієту.\nМій вік: {age}.\nМій зріст: {height} см, вага {weight} кг.\n\nМета: {goal}.\n\nДякую!",
+        )?;
+        let variables = Variables::from([("age", "30".to_string()), ("weight", "80".to_string())]);
+        assert_eq!(
+            template.render(&variables),
+            "Склади дієту.\nМій вік: 30.\n\nДякую!"
+        );
+        assert_eq!(
+            template.render(&Variables::new()),
+            "Склади дієту.\n\nДякую!"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn blank_values_count_as_missing() -> Result<()> {
+        let template = template(PromptKind::Diet, "Привіт.\nМета: {goal}.")?;
+        let variables = Variables::from([("goal", "  ".to_string())]);
+        assert_eq!(template.render(&variables), "Привіт.");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renders_profile_without_debug_text() -> Result<()> {
+        let db = InMemoryDb::new();
+        db.insert_user("Test", "+380000000000", &Some(UserId(1)))
+            .await?;
+        db.add_age("+380000000000", 30).await?;
+        db.add_height_and_weight("+380000000000", 185, 90).await?;
+        let user = db.get_user("+380000000000").await?;
+
+        let variables = profile_variables(&db, &user).await?;
+        let prompt = template(PromptKind::GymTraining, PROMPT_GYM_TRAINING)?.render(&variables);
+        assert!(prompt.contains("Мій вік: 30."));
+        assert!(prompt.contains("Мій зріст: 185 см."));
+        assert!(prompt.contains("Моя вага: 90 кг."));
+        assert!(!prompt.contains("Some("));
+        assert!(!prompt.contains("None"));
+        assert!(!prompt.contains('{'));
+        // Nothing measured or answered yet.
+        assert!(!prompt.contains("заміри"));
+        assert!(!prompt.contains("мета"));
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_real_notes() {
+        assert_eq!(
+            user_notes(Some("болить коліно.")).as_deref(),
+            Some("болить коліно.")
+        );
+        assert_eq!(
+            user_notes(Some("  без бігу!  ")).as_deref(),
+            Some("без бігу!")
+        );
+        assert_eq!(user_notes(Some(".")), None);
+        assert_eq!(user_notes(Some(" -. ")), None);
+        assert_eq!(user_notes(Some("")), None);
+        assert_eq!(user_notes(None), None);
+    }
+}
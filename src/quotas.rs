@@ -0,0 +1,172 @@
+use crate::db::repository::Repository;
+use crate::errors::{Errors, Result};
+use chrono::{DateTime, Datelike, NaiveTime, Utc};
+use uuid::Uuid;
+
+/// Token limits of a single user, `None` means unlimited.
+pub struct Quotas {
+    pub daily: Option<i64>,
+    pub monthly: Option<i64>,
+    /// Telegram ids of the users allowed to see the spend report.
+    pub admins: Vec<u64>,
+}
+
+impl Quotas {
+    /// Reads `DAILY_TOKEN_QUOTA`, `MONTHLY_TOKEN_QUOTA` and the comma separated `ADMIN_IDS`.
+    pub fn from_env() -> Result<Self> {
+        let quota = |name| {
+            dotenv::var(name)
+                .ok()
+                .map(|quota| quota.trim().parse::<i64>())
+                .transpose()
+        };
+        let admins = dotenv::var("ADMIN_IDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(str::parse)
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(Self {
+            daily: quota("DAILY_TOKEN_QUOTA")?,
+            monthly: quota("MONTHLY_TOKEN_QUOTA")?,
+            admins,
+        })
+    }
+
+    pub fn is_admin(&self, telegram_id: u64) -> bool {
+        self.admins.contains(&telegram_id)
+    }
+
+    /// Fails when the user has already spent the daily or the monthly limit.
+    pub async fn check(&self, db: &dyn Repository, user_id: Uuid) -> Result<()> {
+        let now = Utc::now();
+        if let Some(daily) = self.daily {
+            if db.get_tokens_used(user_id, start_of_day(now)).await? >= daily {
+                log::warn!("User {} reached the daily token quota", user_id);
+                return Err(Errors::DailyQuotaExceeded);
+            }
+        }
+        if let Some(monthly) = self.monthly {
+            if db.get_tokens_used(user_id, start_of_month(now)).await? >= monthly {
+                log::warn!("User {} reached the monthly token quota", user_id);
+                return Err(Errors::MonthlyQuotaExceeded);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn start_of_day(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+pub fn start_of_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    let today = now.date_naive();
+    (today - chrono::Days::new(u64::from(today.day0())))
+        .and_time(NaiveTime::MIN)
+        .and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consts::{FEATURE_COACH, FEATURE_TRAINING};
+    use crate::db::in_memory::InMemoryDb;
+    use chrono::TimeZone;
+    use teloxide::types::UserId;
+
+    fn time(year: i32, month: u32, day: u32, hour: u32) -> Option<DateTime<Utc>> {
+        Utc.with_ymd_and_hms(year, month, day, hour, 59, 59)
+            .single()
+    }
+
+    fn midnight(year: i32, month: u32, day: u32) -> Option<DateTime<Utc>> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).single()
+    }
+
+    fn quotas(daily: Option<i64>, monthly: Option<i64>) -> Quotas {
+        Quotas {
+            daily,
+            monthly,
+            admins: vec![],
+        }
+    }
+
+    async fn user_with_usage(db: &InMemoryDb, phone_number: &str, tokens: i32) -> Result<Uuid> {
+        db.insert_user("Test", phone_number, &Some(UserId(1)))
+            .await?;
+        let user_id = db.get_user(phone_number).await?.id;
+        db.add_token_usage(user_id, FEATURE_TRAINING, tokens, tokens)
+            .await?;
+        db.add_token_usage(user_id, FEATURE_COACH, tokens, tokens)
+            .await?;
+        Ok(user_id)
+    }
+
+    #[test]
+    fn day_starts_at_midnight() {
+        assert_eq!(
+            time(2024, 3, 15, 23).map(start_of_day),
+            midnight(2024, 3, 15)
+        );
+        assert_eq!(
+            midnight(2024, 3, 15).map(start_of_day),
+            midnight(2024, 3, 15)
+        );
+    }
+
+    #[test]
+    fn month_starts_on_its_first_day() {
+        assert_eq!(
+            time(2024, 1, 31, 23).map(start_of_month),
+            midnight(2024, 1, 1)
+        );
+        assert_eq!(
+            midnight(2024, 3, 1).map(start_of_month),
+            midnight(2024, 3, 1)
+        );
+        assert_eq!(
+            time(2024, 2, 29, 12).map(start_of_month),
+            midnight(2024, 2, 1)
+        );
+        assert_eq!(
+            time(2023, 12, 31, 23).map(start_of_month),
+            midnight(2023, 12, 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn allows_usage_under_quota() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user_id = user_with_usage(&db, "+380000000000", 100).await?;
+        quotas(Some(401), Some(401)).check(&db, user_id).await?;
+        quotas(None, None).check(&db, user_id).await
+    }
+
+    #[tokio::test]
+    async fn stops_at_daily_quota() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user_id = user_with_usage(&db, "+380000000000", 100).await?;
+        let result = quotas(Some(400), Some(10_000)).check(&db, user_id).await;
+        assert!(matches!(result, Err(Errors::DailyQuotaExceeded)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stops_at_monthly_quota() -> Result<()> {
+        let db = InMemoryDb::new();
+        let user_id = user_with_usage(&db, "+380000000000", 100).await?;
+        let result = quotas(None, Some(400)).check(&db, user_id).await;
+        assert!(matches!(result, Err(Errors::MonthlyQuotaExceeded)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn counts_only_own_usage() -> Result<()> {
+        let db = InMemoryDb::new();
+        user_with_usage(&db, "+380000000000", 1_000).await?;
+        let user_id = user_with_usage(&db, "+380111111111", 10).await?;
+        quotas(Some(100), Some(100)).check(&db, user_id).await
+    }
+}
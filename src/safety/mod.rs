@@ -0,0 +1,2 @@
+pub mod injection;
+pub mod plans;
@@ -0,0 +1,480 @@
+use crate::consts::COMMON_EQUIPMENT;
+use crate::db::models::Users;
+use crate::db::repository::Repository;
+use crate::errors::Result;
+use crate::plans::diet::DietPlan;
+use crate::plans::training::TrainingPlan;
+
+/// No adult diet goes below this without a doctor watching it.
+const MIN_DAILY_KCAL: f64 = 1200.0;
+/// Teenagers are still growing, their floor is higher.
+const MIN_TEEN_DAILY_KCAL: f64 = 1600.0;
+/// Roughly the basal metabolism, a diet below it is an extreme deficit.
+const MIN_KCAL_PER_KG: f64 = 20.0;
+
+const MAX_DAILY_SETS: u32 = 30;
+/// For teenagers and people over 60.
+const MAX_DAILY_SETS_SENSITIVE: u32 = 20;
+
+/// Words that turn a mention of a body part into a stated injury.
+const INJURY_WORDS: [&str; 14] = [
+    "болить",
+    "болі",
+    "біль",
+    "травм",
+    "пошкодж",
+    "операці",
+    "грижа",
+    "протруз",
+    "артрит",
+    "артроз",
+    "розрив",
+    "pain",
+    "injur",
+    "hurt",
+];
+
+/// Equipment texts that need nothing from the inventory: own weight or
+/// furniture every home has.
+const NO_INVENTORY: [&str; 13] = [
+    "власн",
+    "без ",
+    "немає",
+    "нема",
+    "не потрібн",
+    "bodyweight",
+    "none",
+    "стіл",
+    "стін",
+    "підлог",
+    "диван",
+    "ліжк",
+    "сход",
+];
+
+/// A condition the user may mention and the exercises it rules out.
+#[derive(Debug)]
+pub struct Contraindication {
+    /// Used in the warnings, e.g. "травмі коліна".
+    pub name: &'static str,
+    signs: &'static [&'static str],
+    /// Signs name a body part and count only next to one of [`INJURY_WORDS`].
+    body_part: bool,
+    banned: &'static [&'static str],
+}
+
+const CONTRAINDICATIONS: [Contraindication; 4] = [
+    Contraindication {
+        name: "травмі коліна",
+        signs: &["колін", "меніск", "knee"],
+        body_part: true,
+        banned: &[
+            "присід",
+            "випад",
+            "стриб",
+            "біг",
+            "берпі",
+            "squat",
+            "lunge",
+            "jump",
+            "burpee",
+        ],
+    },
+    Contraindication {
+        name: "травмі спини",
+        signs: &["спин", "поперек", "хребет", "back"],
+        body_part: true,
+        banned: &[
+            "станов",
+            "гіперекстенз",
+            "нахил зі штангою",
+            "good morning",
+            "deadlift",
+            "присідання зі штангою",
+        ],
+    },
+    Contraindication {
+        name: "травмі плеча",
+        signs: &["плеч", "shoulder"],
+        body_part: true,
+        banned: &[
+            "жим над головою",
+            "жим стоячи",
+            "армійський жим",
+            "брусах",
+            "тяга до підборіддя",
+            "overhead press",
+            "dips",
+        ],
+    },
+    Contraindication {
+        name: "проблемах з серцем чи тиском",
+        signs: &["гіпертон", "високий тиск", "серц", "аритмі", "hypertension"],
+        body_part: false,
+        banned: &["берпі", "спринт", "hiit", "burpee", "sprint"],
+    },
+];
+
+/// Local rules a generated plan is checked against before it is saved.
+pub struct Limits {
+    pub min_daily_kcal: f64,
+    pub max_daily_sets: u32,
+    /// Conditions found in what the user wrote.
+    pub contraindications: Vec<&'static Contraindication>,
+    /// The home inventory, exercises may use nothing else. `None` in the gym.
+    pub equipment: Option<Vec<String>>,
+}
+
+impl Limits {
+    pub fn new(age: Option<i32>, weight: Option<i32>, text: Option<&str>) -> Self {
+        let teen = age.is_some_and(|age| age < 18);
+        let floor = if teen {
+            MIN_TEEN_DAILY_KCAL
+        } else {
+            MIN_DAILY_KCAL
+        };
+        let by_weight = weight.map_or(0.0, |weight| f64::from(weight) * MIN_KCAL_PER_KG);
+        let max_daily_sets = if age.is_some_and(|age| !(16..60).contains(&age)) {
+            MAX_DAILY_SETS_SENSITIVE
+        } else {
+            MAX_DAILY_SETS
+        };
+        Self {
+            min_daily_kcal: floor.max(by_weight),
+            max_daily_sets,
+            contraindications: text.map(contraindications).unwrap_or_default(),
+            equipment: None,
+        }
+    }
+
+    /// Allows only `equipment` in the exercises.
+    pub fn with_equipment(mut self, equipment: Vec<String>) -> Self {
+        self.equipment = Some(equipment);
+        self
+    }
+
+    /// Limits for the user's age and latest weight and the conditions in `text`.
+    pub async fn for_user(db: &dyn Repository, user: &Users, text: Option<&str>) -> Result<Self> {
+        let weight = db.get_latest_weight(user.id).await?;
+        Ok(Self::new(
+            user.age,
+            weight.map(|weight| weight.weight),
+            text,
+        ))
+    }
+}
+
+/// Conditions mentioned in the text. A body part counts when the same clause
+/// says it hurts or is injured, so "хочу сильні плечі" bans nothing.
+fn contraindications(text: &str) -> Vec<&'static Contraindication> {
+    let text = text.to_lowercase();
+    let clauses: Vec<&str> = text.split(['.', ',', ';', '!', '?', '\n']).collect();
+    CONTRAINDICATIONS
+        .iter()
+        .filter(|condition| {
+            clauses.iter().any(|clause| {
+                condition.signs.iter().any(|sign| clause.contains(sign))
+                    && (!condition.body_part
+                        || INJURY_WORDS.iter().any(|word| clause.contains(word)))
+            })
+        })
+        .collect()
+}
+
+/// Case endings of Ukrainian nouns and adjectives, the longer ones first so
+/// "гантелями" loses "ями" and not just "и".
+const ENDINGS: [&str; 32] = [
+    "ами", "ями", "ого", "ому", "ими", "ові", "еві", "єві", "ою", "ею", "єю", "ів", "їв", "ах",
+    "ях", "ам", "ям", "ом", "ем", "их", "ий", "ій", "ої", "ей", "а", "я", "і", "ї", "и", "у", "ю",
+    "е",
+];
+
+/// Shorter stems would match unrelated words.
+const MIN_STEM_CHARS: usize = 3;
+
+/// The word without its case ending, so "Гиря", "гирею" and "гирі" are all "гир".
+fn stem(word: &str) -> &str {
+    ENDINGS
+        .iter()
+        .find_map(|ending| {
+            word.strip_suffix(ending)
+                .filter(|stem| stem.chars().count() >= MIN_STEM_CHARS)
+        })
+        .unwrap_or(word)
+}
+
+/// Lowercase words of the text, each of them stemmed.
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split_whitespace()
+        .map(|word| stem(word.trim_end_matches('ь')))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether every item the exercise names is in the inventory or needs none.
+fn has_equipment(equipment: &str, inventory: &[String]) -> bool {
+    let equipment = equipment.to_lowercase();
+    if NO_INVENTORY.iter().any(|word| equipment.contains(word)) {
+        return true;
+    }
+    let owned: Vec<String> = inventory.iter().map(|item| normalize(item)).collect();
+    equipment
+        .replace(" та ", ",")
+        .replace(" і ", ",")
+        .replace(" й ", ",")
+        .replace(" на ", ",")
+        .split([',', ';', '/', '+'])
+        .map(normalize)
+        .filter(|item| item.chars().any(char::is_alphabetic))
+        .all(|item| {
+            owned
+                .iter()
+                .any(|owned| item.contains(owned.as_str()) || owned.contains(item.as_str()))
+        })
+}
+
+/// Common equipment the exercise name mentions, e.g. "Штанга" in "Присідання зі штангою".
+fn named_equipment(name: &str) -> Vec<&'static str> {
+    let words = normalize(name);
+    let words: Vec<&str> = words.split(' ').collect();
+    COMMON_EQUIPMENT
+        .iter()
+        .filter(|item| words.contains(&normalize(item).as_str()))
+        .copied()
+        .collect()
+}
+
+pub fn diet_hazards(plan: &DietPlan, limits: &Limits) -> Vec<String> {
+    plan.days
+        .iter()
+        .filter(|day| day.totals().kcal < limits.min_daily_kcal)
+        .map(|day| {
+            format!(
+                "{}: {:.0} ккал, це менше безпечного мінімуму {:.0} ккал",
+                day.day,
+                day.totals().kcal,
+                limits.min_daily_kcal
+            )
+        })
+        .collect()
+}
+
+pub fn training_hazards(plan: &TrainingPlan, limits: &Limits) -> Vec<String> {
+    let mut hazards = Vec::new();
+    for day in &plan.days {
+        let sets: u32 = day.exercises.iter().map(|exercise| exercise.sets).sum();
+        if sets > limits.max_daily_sets {
+            hazards.push(format!(
+                "{}: {} підходів за день, безпечно не більше {}",
+                day.day, sets, limits.max_daily_sets
+            ));
+        }
+        for exercise in &day.exercises {
+            let name = exercise.name.to_lowercase();
+            if let Some(inventory) = &limits.equipment {
+                // Without the field the model may still name the equipment in the exercise.
+                let equipment = match &exercise.equipment {
+                    Some(equipment) => vec![equipment.as_str()],
+                    None => named_equipment(&exercise.name),
+                };
+                for equipment in equipment {
+                    if !has_equipment(equipment, inventory) {
+                        hazards.push(format!(
+                            "{}: {} потребує інвентар \"{}\", якого немає вдома",
+                            day.day, exercise.name, equipment
+                        ));
+                    }
+                }
+            }
+            for condition in &limits.contraindications {
+                if condition.banned.iter().any(|banned| name.contains(banned)) {
+                    hazards.push(format!(
+                        "{}: {} не можна при {}",
+                        day.day, exercise.name, condition.name
+                    ));
+                }
+            }
+        }
+    }
+    hazards
+}
+
+/// Puts the problems the model didn't fix in front of the plan's notes.
+pub fn warning(hazards: &[String], notes: Option<&str>) -> String {
+    let warning = format!(
+        "⚠️ Увага, цей план може бути небезпечним: {}. Перед початком порадься з лікарем або тренером.",
+        hazards.join("; ")
+    );
+    match notes {
+        Some(notes) => format!("{}\n\n{}", warning, notes),
+        None => warning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plans::diet::{DietDay, Food, Meal, Nutrients};
+    use crate::plans::training::{Exercise, TrainingDay};
+
+    fn training(exercises: &[(&str, u32, Option<&str>)]) -> TrainingPlan {
+        TrainingPlan {
+            days: vec![TrainingDay {
+                day: "Понеділок".to_string(),
+                focus: None,
+                duration_minutes: 60,
+                exercises: exercises
+                    .iter()
+                    .map(|(name, sets, equipment)| Exercise {
+                        name: name.to_string(),
+                        sets: *sets,
+                        reps: "10".to_string(),
+                        rest_seconds: 60,
+                        equipment: equipment.map(str::to_string),
+                    })
+                    .collect(),
+            }],
+            notes: None,
+        }
+    }
+
+    fn names(text: &str) -> Vec<&'static str> {
+        contraindications(text)
+            .iter()
+            .map(|condition| condition.name)
+            .collect()
+    }
+
+    #[test]
+    fn calorie_floor_depends_on_age_and_weight() {
+        assert_eq!(Limits::new(None, None, None).min_daily_kcal, 1200.0);
+        assert_eq!(Limits::new(Some(30), Some(50), None).min_daily_kcal, 1200.0);
+        assert_eq!(Limits::new(Some(30), Some(90), None).min_daily_kcal, 1800.0);
+        assert_eq!(Limits::new(Some(16), Some(50), None).min_daily_kcal, 1600.0);
+        assert_eq!(
+            Limits::new(Some(17), Some(100), None).min_daily_kcal,
+            2000.0
+        );
+        assert_eq!(Limits::new(Some(18), Some(50), None).min_daily_kcal, 1200.0);
+    }
+
+    #[test]
+    fn fewer_sets_for_teenagers_and_seniors() {
+        let sets = |age| Limits::new(age, None, None).max_daily_sets;
+        assert_eq!(sets(None), MAX_DAILY_SETS);
+        assert_eq!(sets(Some(15)), MAX_DAILY_SETS_SENSITIVE);
+        assert_eq!(sets(Some(16)), MAX_DAILY_SETS);
+        assert_eq!(sets(Some(59)), MAX_DAILY_SETS);
+        assert_eq!(sets(Some(60)), MAX_DAILY_SETS_SENSITIVE);
+    }
+
+    #[test]
+    fn body_part_counts_only_with_injury_in_same_clause() {
+        assert!(names("хочу сильні плечі").is_empty());
+        assert!(names("хочу сильні плечі, болить голова").is_empty());
+        assert_eq!(names("болить коліно."), ["травмі коліна"]);
+        assert_eq!(names("Коліно болить, хочу сильні плечі"), ["травмі коліна"]);
+        assert_eq!(
+            names("Була операція на меніску\nгрижа в попереку"),
+            ["травмі коліна", "травмі спини"]
+        );
+        assert_eq!(names("my knee hurts"), ["травмі коліна"]);
+    }
+
+    #[test]
+    fn heart_conditions_need_no_injury_word() {
+        assert_eq!(names("у мене гіпертонія"), ["проблемах з серцем чи тиском"]);
+    }
+
+    #[test]
+    fn equipment_matches_any_word_form() {
+        let inventory = ["Гантелі".to_string(), "Фітбол".to_string()];
+        assert!(has_equipment("гантелями 10 кг", &inventory));
+        assert!(has_equipment("Гантель", &inventory));
+        assert!(has_equipment("фітбол та гантелі", &inventory));
+        assert!(has_equipment("Власна вага", &inventory));
+        assert!(has_equipment("Стілець", &inventory));
+        assert!(!has_equipment("Штанга", &inventory));
+        assert!(!has_equipment("гантелі і штанга", &inventory));
+        assert!(!has_equipment("Гантелі", &[]));
+    }
+
+    #[test]
+    fn equipment_matches_case_endings() {
+        let inventory = [
+            "Гиря".to_string(),
+            "Лава".to_string(),
+            "Гумова стрічка".to_string(),
+        ];
+        assert!(has_equipment("гирею", &inventory));
+        assert!(has_equipment("Гирі 16 кг", &inventory));
+        assert!(has_equipment("лавою", &inventory));
+        assert!(has_equipment("на лаві", &inventory));
+        assert!(has_equipment("гумовою стрічкою", &inventory));
+        assert!(!has_equipment("штанга на лаві", &inventory));
+        assert!(!has_equipment("гантелями", &inventory));
+    }
+
+    #[test]
+    fn finds_equipment_in_exercise_name() {
+        assert_eq!(named_equipment("Присідання зі штангою"), ["Штанга"]);
+        assert_eq!(named_equipment("Жим гантелей на лаві"), ["Гантелі", "Лава"]);
+        assert_eq!(named_equipment("Підтягування на турніку"), ["Турнік"]);
+        assert!(named_equipment("Віджимання").is_empty());
+    }
+
+    #[test]
+    fn finds_training_hazards() {
+        let limits = Limits::new(Some(65), None, Some("болить коліно"))
+            .with_equipment(vec!["Гантелі".to_string()]);
+        let plan = training(&[
+            ("Присідання з гантелями", 4, Some("Гантелі")),
+            ("Жим лежачи", 10, Some("Штанга")),
+            ("Віджимання", 10, None),
+            ("Тяга штанги в нахилі", 3, None),
+        ]);
+        assert_eq!(
+            training_hazards(&plan, &limits),
+            [
+                "Понеділок: 27 підходів за день, безпечно не більше 20",
+                "Понеділок: Присідання з гантелями не можна при травмі коліна",
+                "Понеділок: Жим лежачи потребує інвентар \"Штанга\", якого немає вдома",
+                "Понеділок: Тяга штанги в нахилі потребує інвентар \"Штанга\", якого немає вдома",
+            ]
+        );
+        let safe = training(&[
+            ("Віджимання", 4, Some("Власна вага")),
+            ("Тяга гантелі в нахилі", 4, None),
+        ]);
+        assert!(training_hazards(&safe, &limits).is_empty());
+    }
+
+    #[test]
+    fn finds_days_under_calorie_floor() {
+        let day = |kcal| DietDay {
+            day: "Понеділок".to_string(),
+            meals: vec![Meal {
+                name: "Обід".to_string(),
+                foods: vec![Food {
+                    name: "Гречка".to_string(),
+                    grams: 300,
+                    nutrients: Nutrients {
+                        kcal,
+                        protein: 30.0,
+                        fat: 10.0,
+                        carbs: 200.0,
+                    },
+                }],
+            }],
+        };
+        let plan = DietPlan {
+            days: vec![day(1000.0), day(1800.0)],
+            notes: None,
+        };
+        assert_eq!(
+            diet_hazards(&plan, &Limits::new(Some(30), Some(80), None)),
+            ["Понеділок: 1000 ккал, це менше безпечного мінімуму 1600 ккал"]
+        );
+    }
+}
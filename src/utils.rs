@@ -1,13 +1,28 @@
+use crate::api_calls::admin::{reload_prompts, usage};
 use crate::api_calls::basic_methods::{cancel, help, invalid_state, start};
-use crate::api_calls::diet::add_diet;
+use crate::api_calls::coach::coach_chat;
+use crate::api_calls::diet::{add_diet, edit_diet};
+use crate::api_calls::equipment::equipment_menu;
+use crate::api_calls::export::export;
 use crate::api_calls::menu::{
-    change_menu, diet_menu, gym_training_menu, home_training_menu, update_data, update_data_data,
-    update_size,
+    change_menu, delete_account, diet_menu, gym_training_menu, home_training_menu, update_data,
+    update_data_data, update_size,
 };
-use crate::api_calls::registration::{get_age, get_email, get_height_and_weight, get_number};
-use crate::api_calls::trainings::add_training;
-use crate::consts::{PROMPT_MSG_AGE, PROMPT_MSG_HEIGHT, PROMPT_MSG_WEIGHT};
-use crate::db::models::Users;
+use crate::api_calls::registration::{
+    get_age, get_days_per_week, get_email, get_experience, get_goal, get_height_and_weight,
+    get_number, get_session_length,
+};
+use crate::api_calls::trainings::{
+    add_training, choose_training_version, edit_training, training_version_menu,
+};
+use crate::async_openai::client::OpenAiClient;
+use crate::async_openai::config::ModelSettings;
+use crate::async_openai::mock::MockProvider;
+use crate::async_openai::provider::{LlmProvider, LlmProviders};
+use crate::db::database::Db;
+use crate::db::in_memory::InMemoryDb;
+use crate::db::repository::Repository;
+use crate::db::storage::PostgresStorage;
 use crate::models::Command;
 use crate::models::State;
 use colored::*;
@@ -15,14 +30,18 @@ use log::{Level, LevelFilter};
 use regex::Regex;
 use std::collections::HashMap;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::thread::ThreadId;
-use teloxide::dispatching::dialogue::InMemStorage;
+use teloxide::dispatching::dialogue::{ErasedStorage, InMemStorage, Storage};
 use teloxide::dispatching::{dialogue, DpHandlerDescription};
 use teloxide::dptree;
 use teloxide::dptree::{case, Handler};
 use teloxide::prelude::*;
 use teloxide::types::{KeyboardButton, KeyboardMarkup};
 
+/// Telegram refuses messages longer than 4096 characters.
+const MAX_MESSAGE_LENGTH: usize = 4000;
+
 pub fn schema() -> Handler<'static, DependencyMap, crate::errors::Result<()>, DpHandlerDescription>
 {
     let command_handler = teloxide::filter_command::<Command, _>()
@@ -31,17 +50,49 @@ pub fn schema() -> Handler<'static, DependencyMap, crate::errors::Result<()>, Dp
                 .branch(case![Command::Help].endpoint(help))
                 .branch(case![Command::Start].endpoint(start)),
         )
-        .branch(case![Command::Cancel].endpoint(cancel));
+        .branch(case![Command::Cancel].endpoint(cancel))
+        .branch(case![Command::Export].endpoint(export))
+        .branch(case![Command::Usage].endpoint(usage))
+        .branch(case![Command::ReloadPrompts].endpoint(reload_prompts));
 
     let callback_query_handler = Update::filter_message()
         .branch(case![State::GetEmail { phone_number }].endpoint(get_email))
         .branch(case![State::GetAge { phone_number }].endpoint(get_age))
-        .branch(case![State::GetWeightAndHeight { phone_number }].endpoint(get_height_and_weight));
+        .branch(case![State::GetWeightAndHeight { phone_number }].endpoint(get_height_and_weight))
+        .branch(
+            case![State::GetGoal {
+                phone_number,
+                registration
+            }]
+            .endpoint(get_goal),
+        )
+        .branch(
+            case![State::GetExperience {
+                phone_number,
+                registration
+            }]
+            .endpoint(get_experience),
+        )
+        .branch(
+            case![State::GetDaysPerWeek {
+                phone_number,
+                registration
+            }]
+            .endpoint(get_days_per_week),
+        )
+        .branch(
+            case![State::GetSessionLength {
+                phone_number,
+                registration
+            }]
+            .endpoint(get_session_length),
+        );
 
     let message_handler = Update::filter_message()
         .branch(command_handler)
         .branch(case![State::GetPhoneNumber].endpoint(get_number))
         .branch(case![State::HomeTrainingMenu { phone_number }].endpoint(home_training_menu))
+        .branch(case![State::HomeEquipment { phone_number }].endpoint(equipment_menu))
         .branch(case![State::GymTrainingMenu { phone_number }].endpoint(gym_training_menu))
         .branch(case![State::DietMenu { phone_number }].endpoint(diet_menu))
         .branch(case![State::ChangeMenu { phone_number }].endpoint(change_menu))
@@ -52,14 +103,134 @@ pub fn schema() -> Handler<'static, DependencyMap, crate::errors::Result<()>, Dp
             }]
             .endpoint(add_training),
         )
+        .branch(
+            case![State::TrainingHistory {
+                phone_number,
+                training_state
+            }]
+            .endpoint(choose_training_version),
+        )
+        .branch(
+            case![State::TrainingVersion {
+                phone_number,
+                training_state,
+                training_id
+            }]
+            .endpoint(training_version_menu),
+        )
         .branch(case![State::AddDiet { phone_number }].endpoint(add_diet))
         .branch(case![State::Data { phone_number }].endpoint(update_data))
         .branch(case![State::UpdateData { phone_number }].endpoint(update_data_data))
         .branch(case![State::UpdateSize { phone_number }].endpoint(update_size))
+        .branch(case![State::DeleteAccount { phone_number }].endpoint(delete_account))
+        .branch(case![State::CoachChat { phone_number }].endpoint(coach_chat))
+        .branch(
+            case![State::EditTraining {
+                phone_number,
+                training_state
+            }]
+            .endpoint(edit_training),
+        )
+        .branch(case![State::EditDiet { phone_number }].endpoint(edit_diet))
         .branch(callback_query_handler)
         .branch(dptree::endpoint(invalid_state));
 
-    dialogue::enter::<Update, InMemStorage<State>, State, _>().branch(message_handler)
+    dialogue::enter::<Update, ErasedStorage<State>, State, _>().branch(message_handler)
+}
+
+/// Creates the repository chosen by `DATABASE_BACKEND` ("postgres" by default, or "memory")
+/// together with the dialogue storage.
+pub async fn make_storages(
+) -> crate::errors::Result<(Arc<dyn Repository>, Arc<ErasedStorage<State>>)> {
+    if let Ok("memory") = dotenv::var("DATABASE_BACKEND").as_deref() {
+        log::info!("Using in-memory database, all data will be lost after restart");
+        return Ok((
+            Arc::new(InMemoryDb::new()),
+            InMemStorage::<State>::new().erase(),
+        ));
+    }
+
+    let database_url = dotenv::var("DATABASE_URL")?;
+    let pool_size = dotenv::var("DATABASE_POOL_SIZE")
+        .ok()
+        .map(|size| size.parse())
+        .transpose()?;
+
+    let db = Arc::new(Db::new(&database_url, pool_size)?);
+    db.run_migrations().await?;
+    db.check_schema().await?;
+
+    let storage = make_dialogue_storage(Arc::clone(&db));
+    Ok((db, storage))
+}
+
+/// Creates the language model chosen by `LLM_PROVIDER`:
+/// "openai" (default) needs `OPENAI_API_KEY`,
+/// "compatible" sends the requests to `LLM_BASE_URL`, for example a self-hosted model server,
+/// "mock" answers offline with canned text.
+/// The model, sampling and system prompt of both remote backends are read per feature
+/// by [`ModelSettings::from_env`].
+pub fn make_llm_providers() -> crate::errors::Result<Arc<LlmProviders>> {
+    let providers = match dotenv::var("LLM_PROVIDER").as_deref() {
+        Ok("mock") => {
+            log::info!("Using mock language model, no requests leave the bot");
+            let mock: Arc<dyn LlmProvider> = Arc::new(MockProvider::new());
+            LlmProviders::new(|_| Ok(Arc::clone(&mock)))?
+        }
+        Ok("compatible") => {
+            let base_url = dotenv::var("LLM_BASE_URL")?;
+            let api_key = dotenv::var("OPENAI_API_KEY").unwrap_or_default();
+            log::info!("Using models served at {}", base_url);
+            LlmProviders::new(|feature| {
+                let settings = model_settings(feature)?;
+                Ok(Arc::new(OpenAiClient::new(
+                    api_key.clone(),
+                    Some(base_url.clone()),
+                    settings,
+                )))
+            })?
+        }
+        _ => {
+            let api_key = dotenv::var("OPENAI_API_KEY")?;
+            log::info!("Using OpenAI models");
+            LlmProviders::new(|feature| {
+                let settings = model_settings(feature)?;
+                Ok(Arc::new(OpenAiClient::new(api_key.clone(), None, settings)))
+            })?
+        }
+    };
+    Ok(Arc::new(providers))
+}
+
+fn model_settings(feature: Option<&str>) -> crate::errors::Result<ModelSettings> {
+    let settings = ModelSettings::from_env(feature)?;
+    log::info!(
+        "Model for {}: {}, temperature {:?}, max tokens {:?}, system prompt {}",
+        feature.unwrap_or("other features"),
+        settings.model,
+        settings.temperature,
+        settings.max_tokens,
+        if settings.system_prompt.is_some() {
+            "set"
+        } else {
+            "not set"
+        }
+    );
+    Ok(settings)
+}
+
+/// Creates the dialogue storage chosen by `DIALOGUE_STORAGE` ("postgres" by default, or "memory").
+fn make_dialogue_storage(db: Arc<Db>) -> Arc<ErasedStorage<State>> {
+    match dotenv::var("DIALOGUE_STORAGE").as_deref() {
+        Ok("memory") => {
+            log::info!("Using in-memory dialogue storage");
+            InMemStorage::<State>::new().erase()
+        }
+        _ => {
+            log::info!("Using postgres dialogue storage");
+            PostgresStorage::new(db).erase()
+        }
+    }
 }
 
 /// Creates a keyboard made by buttons in a big column.
@@ -78,6 +249,15 @@ pub fn make_keyboard(menu_buttons: Vec<String>) -> KeyboardMarkup {
     KeyboardMarkup::new(keyboard)
 }
 
+/// Creates a keyboard with one button in every row.
+pub fn make_column_keyboard(buttons: Vec<String>) -> KeyboardMarkup {
+    KeyboardMarkup::new(
+        buttons
+            .into_iter()
+            .map(|button| vec![KeyboardButton::new(button)]),
+    )
+}
+
 pub fn init_logging() -> crate::errors::Result<()> {
     // Logging lib errors and all app logs
     let log_level = LevelFilter::Debug;
@@ -142,42 +322,19 @@ pub fn is_valid_email(email: &str) -> crate::errors::Result<bool> {
     Ok(re.is_match(email))
 }
 
-pub fn format_prompt(prompt: Option<&str>, const1: &str, const2: &str, user: Users) -> String {
-    if let Some(prompt) = prompt {
-        if prompt.contains('.') {
-            format!(
-                "{} {} {:?} {} {:?} {} {:?}",
-                const2,
-                PROMPT_MSG_AGE,
-                user.age,
-                PROMPT_MSG_HEIGHT,
-                user.height,
-                PROMPT_MSG_WEIGHT,
-                user.weight
-            )
-        } else {
-            format!(
-                "{}{} {} {:?} {} {:?} {} {:?}",
-                const1,
-                prompt,
-                PROMPT_MSG_AGE,
-                user.age,
-                PROMPT_MSG_HEIGHT,
-                user.height,
-                PROMPT_MSG_WEIGHT,
-                user.weight
-            )
+/// Joins the lines into messages that fit into Telegram limits.
+pub fn split_message(lines: &[String]) -> Vec<String> {
+    let mut messages = Vec::new();
+    let mut message = String::new();
+    for line in lines {
+        if !message.is_empty() && message.len() + line.len() + 1 > MAX_MESSAGE_LENGTH {
+            messages.push(std::mem::take(&mut message));
         }
-    } else {
-        format!(
-            "{} {} {:?} {} {:?} {} {:?}",
-            const2,
-            PROMPT_MSG_AGE,
-            user.age,
-            PROMPT_MSG_HEIGHT,
-            user.height,
-            PROMPT_MSG_WEIGHT,
-            user.weight
-        )
+        if !message.is_empty() {
+            message.push('\n');
+        }
+        message.push_str(line);
     }
+    messages.push(message);
+    messages
 }